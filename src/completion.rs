@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use crate::game_options::NetworkFilter;
+use crate::resource_util::{delete_save, load_save, write_save};
+
+const SAVE_PATH: &str = "every_station.json";
+
+/// Progress through a game of guessing every station, as saved to disk.
+/// Stations are saved by name so that a save still loads if the station data changes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CompletionSave {
+    pub found: Vec<String>,
+    pub elapsed_secs: u64,
+    // Which networks' stations have to be found
+    #[serde(default)]
+    pub networks: NetworkFilter
+}
+
+impl CompletionSave {
+    /// Loads the saved progress, if there is any.
+    pub fn load() -> Option<Self> {
+        load_save(SAVE_PATH)
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        write_save(SAVE_PATH, self)
+    }
+
+    /// Removes the saved progress so the next game starts from scratch.
+    pub fn delete() -> std::io::Result<()> {
+        delete_save(SAVE_PATH)
+    }
+}
@@ -0,0 +1,213 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::game_phase::GamePhase;
+use crate::hints::Hint;
+use crate::resource_util::{load_save, write_save};
+use crate::stations::Station;
+
+const STATS_PATH: &str = "daily_stats.json";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The day of the current puzzle, counted from the Unix epoch.
+/// Everyone gets a new puzzle at midnight UTC.
+pub fn today() -> u64 {
+    seconds_since_epoch() / SECONDS_PER_DAY
+}
+
+/// How long until the next puzzle.
+pub fn until_tomorrow() -> Duration {
+    Duration::from_secs(SECONDS_PER_DAY - seconds_since_epoch() % SECONDS_PER_DAY)
+}
+
+fn seconds_since_epoch() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Fingerprint of the station data, so that everyone playing
+/// the same day with the same data gets the same target.
+pub fn dataset_version(stations: &[Station]) -> u64 {
+    stations.iter().fold(FNV_OFFSET, |hash, station| fnv(hash, station.name.as_bytes()))
+}
+
+/// Picks the index of the day's target out of a pool of the given size.
+pub fn daily_index(day: u64, version: u64, pool_size: usize) -> usize {
+    let hash = fnv(fnv(FNV_OFFSET, &version.to_le_bytes()), &day.to_le_bytes());
+    (hash % pool_size.max(1) as u64) as usize
+}
+
+// FNV-1a, unlike the standard library's hasher it's guaranteed not to change
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// How the attempt that counted for a day went.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub day: u64,
+    // How the game ended, not just whether it was won
+    pub outcome: GamePhase,
+    pub guesses: usize,
+    pub hints: Vec<Hint>,
+    pub elapsed_secs: u64
+}
+
+/// Results of past daily puzzles, only the first attempt each day counts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DailyStats {
+    pub played: u32,
+    pub won: u32,
+    pub current_streak: u32,
+    pub best_streak: u32,
+    // Hints used over every counted attempt
+    #[serde(default)]
+    pub hints_used: u32,
+    #[serde(default)]
+    pub last_result: Option<DailyResult>,
+    last_played: Option<u64>,
+    last_won: Option<u64>
+}
+
+impl DailyStats {
+    pub fn load() -> Self {
+        load_save(STATS_PATH).unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        write_save(STATS_PATH, self)
+    }
+
+    /// Records an attempt at the day's puzzle, returns
+    /// `false` if it has already been attempted.
+    pub fn start(&mut self, day: u64) -> bool {
+        if self.last_played == Some(day) {
+            return false
+        }
+
+        self.played += 1;
+        self.last_played = Some(day);
+        // The streak is broken if yesterday's puzzle wasn't solved
+        if self.last_won != day.checked_sub(1) {
+            self.current_streak = 0;
+        }
+        true
+    }
+
+    /// Records how the day's counted attempt ended.
+    pub fn finish(&mut self, result: DailyResult) {
+        if result.outcome == GamePhase::Won {
+            self.won += 1;
+            self.last_won = Some(result.day);
+            self.current_streak += 1;
+            self.best_streak = self.best_streak.max(self.current_streak);
+        } else {
+            self.current_streak = 0;
+        }
+        self.hints_used += result.hints.len() as u32;
+        self.last_result = Some(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(day: u64, outcome: GamePhase) -> DailyResult {
+        DailyResult { day, outcome, guesses: 5, hints: vec![], elapsed_secs: 60 }
+    }
+
+    fn stations(names: &[&str]) -> Vec<Station> {
+        names.iter()
+            .map(|name| Station { name: name.to_string(), ..Default::default() })
+            .collect()
+    }
+
+    #[test]
+    fn streak_continues_on_consecutive_days() {
+        let mut stats = DailyStats::default();
+        for day in 10..13 {
+            assert!(stats.start(day));
+            stats.finish(result(day, GamePhase::Won));
+        }
+        assert_eq!((stats.played, stats.won), (3, 3));
+        assert_eq!((stats.current_streak, stats.best_streak), (3, 3));
+    }
+
+    #[test]
+    fn streak_breaks_after_a_missed_day() {
+        let mut stats = DailyStats::default();
+        stats.start(10);
+        stats.finish(result(10, GamePhase::Won));
+        stats.start(11);
+        stats.finish(result(11, GamePhase::Won));
+
+        // Nothing was played on day 12
+        assert!(stats.start(13));
+        assert_eq!(stats.current_streak, 0);
+        stats.finish(result(13, GamePhase::Won));
+        assert_eq!((stats.current_streak, stats.best_streak), (1, 2));
+    }
+
+    #[test]
+    fn streak_breaks_after_a_loss() {
+        let mut stats = DailyStats::default();
+        stats.start(10);
+        stats.finish(result(10, GamePhase::Won));
+        stats.start(11);
+        stats.finish(result(11, GamePhase::OutOfGuesses));
+        assert_eq!((stats.current_streak, stats.best_streak), (0, 1));
+
+        stats.start(12);
+        assert_eq!(stats.current_streak, 0);
+    }
+
+    #[test]
+    fn same_day_only_counts_once() {
+        let mut stats = DailyStats::default();
+        assert!(stats.start(10));
+        stats.finish(result(10, GamePhase::Won));
+        assert!(!stats.start(10));
+        assert_eq!((stats.played, stats.won, stats.current_streak), (1, 1, 1));
+    }
+
+    #[test]
+    fn keeps_the_hints_used() {
+        let mut stats = DailyStats::default();
+        stats.start(10);
+        let hinted = DailyResult { day: 10, outcome: GamePhase::Won, guesses: 7, hints: vec![Hint::Zone, Hint::Line], elapsed_secs: 95 };
+        stats.finish(hinted.clone());
+        stats.start(11);
+        stats.finish(DailyResult { day: 11, outcome: GamePhase::GaveUp, guesses: 3, hints: vec![Hint::FirstLetter], elapsed_secs: 30 });
+        assert_eq!(stats.hints_used, 3);
+
+        let saved = serde_json::to_string(&stats).unwrap();
+        let loaded: DailyStats = serde_json::from_str(&saved).unwrap();
+        let last = loaded.last_result.unwrap();
+        assert_eq!((last.outcome, last.hints), (GamePhase::GaveUp, vec![Hint::FirstLetter]));
+        assert_eq!(loaded.hints_used, 3);
+    }
+
+    #[test]
+    fn dataset_version_follows_station_names() {
+        let version = dataset_version(&stations(&["Bank", "Oval"]));
+        assert_eq!(version, dataset_version(&stations(&["Bank", "Oval"])));
+        assert_ne!(version, dataset_version(&stations(&["Bank", "Ovál"])));
+        assert_ne!(version, dataset_version(&stations(&["Oval", "Bank"])));
+        assert_ne!(version, dataset_version(&stations(&["Bank"])));
+    }
+
+    #[test]
+    fn daily_index_is_stable_and_in_range() {
+        let version = dataset_version(&stations(&["Bank", "Oval"]));
+        for day in 0..100 {
+            let index = daily_index(day, version, 7);
+            assert!(index < 7);
+            assert_eq!(index, daily_index(day, version, 7));
+        }
+        assert_eq!(daily_index(5, version, 0), 0);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::CoordinateSystem;
+use crate::network::Network;
+use crate::stations::Station;
+
+/// A way of measuring how close a station is to the target station.
+pub trait DistanceMetric {
+    /// How close a station's position is to the target, 1.0 being at the target
+    /// and 0.0 being far away. The position is `None` for stations not on the map.
+    fn closeness(&self, station_idx: usize, position: Option<&(f32, f32)>) -> f32;
+}
+
+/// Distance between positions on the map image.
+pub struct MapDistance {
+    target_position: Option<(f32, f32)>
+}
+
+impl MapDistance {
+    // Relative to the map's height
+    const FAR: f32 = 0.7;
+
+    pub fn new(target: &Station) -> Self {
+        Self {
+            target_position: average_position(&target.station_positions)
+        }
+    }
+}
+
+impl DistanceMetric for MapDistance {
+    fn closeness(&self, _station_idx: usize, position: Option<&(f32, f32)>) -> f32 {
+        // Stations that aren't on the map can't be measured along it
+        let (Some(position), Some(target_position)) = (position, self.target_position) else {
+            return 0.0
+        };
+        let dx = position.0 - target_position.0;
+        let dy = position.1 - target_position.1;
+        let distance = (dx * dx + dy * dy).sqrt() * CoordinateSystem::REL_Y;
+
+        (1.0 - distance / Self::FAR).max(0.0)
+    }
+}
+
+/// Number of stops along the network, optionally
+/// where each change of line also counts as stops.
+pub struct StopDistance {
+    costs: Vec<Option<usize>>
+}
+
+impl StopDistance {
+    const FAR: f32 = 14.0;
+
+    pub fn new(network: &Network, target_idx: usize, change_cost: usize) -> Self {
+        Self {
+            costs: network.costs_from(target_idx, change_cost)
+        }
+    }
+}
+
+impl DistanceMetric for StopDistance {
+    fn closeness(&self, station_idx: usize, _position: Option<&(f32, f32)>) -> f32 {
+        // Unreachable stations are as far as can be
+        match self.costs.get(station_idx).copied().flatten() {
+            Some(cost) => (1.0 - cost as f32 / Self::FAR).max(0.0),
+            None => 0.0
+        }
+    }
+}
+
+/// Real-world distance between stations.
+pub struct GeoDistance {
+    target_position: Option<(f64, f64)>,
+    positions: Vec<Option<(f64, f64)>>
+}
+
+impl GeoDistance {
+    // Kilometres
+    const FAR: f64 = 12.0;
+
+    pub fn new(stations: &[Station], target_idx: usize) -> Self {
+        Self {
+            target_position: stations[target_idx].geo_position(),
+            positions: stations.iter().map(Station::geo_position).collect()
+        }
+    }
+}
+
+impl DistanceMetric for GeoDistance {
+    fn closeness(&self, station_idx: usize, _position: Option<&(f32, f32)>) -> f32 {
+        let position = self.positions.get(station_idx).copied().flatten();
+        match (position, self.target_position) {
+            (Some(position), Some(target)) => {
+                (1.0 - haversine_km(position, target) / Self::FAR).max(0.0) as f32
+            }
+            // Without a real-world position we can't say how close it is
+            _ => 0.0
+        }
+    }
+}
+
+/// The great-circle distance in kilometres between two (latitude, longitude) positions.
+pub fn haversine_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+
+    let (from_lat, to_lat) = (from.0.to_radians(), to.0.to_radians());
+    let d_lat = to_lat - from_lat;
+    let d_lon = (to.1 - from.1).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2) + from_lat.cos() * to_lat.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// The direction from one (latitude, longitude) position to another as (east, south),
+/// so that it lines up with the screen. This is close enough over a city.
+pub fn geo_direction(from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+    let east = (to.1 - from.1) * from.0.to_radians().cos();
+    let south = from.0 - to.0;
+    (east, south)
+}
+
+fn average_position(positions: &[(f32, f32)]) -> Option<(f32, f32)> {
+    if positions.is_empty() {
+        return None
+    }
+    let mut x = 0.0;
+    let mut y = 0.0;
+    for position in positions {
+        x += position.0;
+        y += position.1;
+    }
+    Some((x / positions.len() as f32, y / positions.len() as f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BANK: (f64, f64) = (51.5133, -0.0886);
+    // Around 13 km north of Bank
+    const NORTH_OF_BANK: (f64, f64) = (51.63, -0.0886);
+    const OXFORD_CIRCUS: (f64, f64) = (51.5152, -0.1418);
+
+    #[test]
+    fn map_closeness_falls_off_with_map_distance() {
+        let target = Station { station_positions: vec![(0.2, 0.3), (0.4, 0.3)], ..Default::default() };
+        let metric = MapDistance::new(&target);
+        assert_eq!(metric.closeness(0, Some(&(0.3, 0.3))), 1.0);
+
+        let far = MapDistance::FAR / CoordinateSystem::REL_Y;
+        assert!(metric.closeness(0, Some(&(0.3, 0.3 + far))) < 1e-6);
+        assert_eq!(metric.closeness(0, Some(&(0.3, 0.3 + far * 2.0))), 0.0);
+        let halfway = metric.closeness(0, Some(&(0.3 + far / 2.0, 0.3)));
+        assert!((halfway - 0.5).abs() < 1e-6, "{}", halfway);
+
+        // Neither end can be off the map
+        assert_eq!(metric.closeness(0, None), 0.0);
+        assert_eq!(MapDistance::new(&Station::default()).closeness(0, Some(&(0.3, 0.3))), 0.0);
+    }
+
+    #[test]
+    fn stop_closeness_falls_off_with_cost() {
+        let metric = StopDistance { costs: vec![Some(0), Some(7), Some(14), Some(20), None] };
+        assert_eq!(metric.closeness(0, None), 1.0);
+        assert_eq!(metric.closeness(1, None), 0.5);
+        assert_eq!(metric.closeness(2, None), 0.0);
+        assert_eq!(metric.closeness(3, None), 0.0);
+        // Unreachable stations
+        assert_eq!(metric.closeness(4, None), 0.0);
+    }
+
+    #[test]
+    fn geo_closeness_falls_off_with_real_distance() {
+        let stations = vec![
+            Station { geo: vec![BANK], ..Default::default() },
+            Station { geo: vec![NORTH_OF_BANK], ..Default::default() },
+            Station::default()
+        ];
+        let metric = GeoDistance::new(&stations, 0);
+        assert_eq!(metric.closeness(0, None), 1.0);
+        assert_eq!(metric.closeness(1, None), 0.0);
+        // Stations without a real-world position
+        assert_eq!(metric.closeness(2, None), 0.0);
+        assert_eq!(GeoDistance::new(&stations, 2).closeness(0, None), 0.0);
+    }
+
+    #[test]
+    fn haversine_km_measures_across_london() {
+        assert_eq!(haversine_km(BANK, BANK), 0.0);
+        let distance = haversine_km(BANK, OXFORD_CIRCUS);
+        assert!((distance - 3.69).abs() < 0.05, "{}", distance);
+        assert_eq!(distance, haversine_km(OXFORD_CIRCUS, BANK));
+    }
+
+    #[test]
+    fn geo_direction_lines_up_with_the_screen() {
+        // Oxford Circus is west of Bank and slightly north
+        let (east, south) = geo_direction(BANK, OXFORD_CIRCUS);
+        assert!(east < 0.0 && south < 0.0);
+        assert!(east.abs() > south.abs());
+        let (east, south) = geo_direction(OXFORD_CIRCUS, BANK);
+        assert!(east > 0.0 && south > 0.0);
+    }
+}
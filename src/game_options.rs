@@ -0,0 +1,531 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::lines::{LineId, RailNetwork};
+use crate::stations::Station;
+
+/// What the player is trying to do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    FindTarget,
+    // The target is shown on the map and has to be named
+    NameStation,
+    // Everyone gets the same target each day
+    Daily,
+    // There is no target, every station has to be named
+    EveryStation
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::FindTarget,
+        GameMode::NameStation,
+        GameMode::Daily,
+        GameMode::EveryStation
+    ];
+
+    // Modes that can be played along with through a game code, the
+    // daily target is already shared and every station has no target
+    pub const SHARED: [GameMode; 2] = [
+        GameMode::FindTarget,
+        GameMode::NameStation
+    ];
+}
+
+impl Display for GameMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameMode::FindTarget => write!(f, "Find the Station"),
+            GameMode::NameStation => write!(f, "Name the Station"),
+            GameMode::Daily => write!(f, "Daily Challenge"),
+            GameMode::EveryStation => write!(f, "Guess Every Station")
+        }
+    }
+}
+
+/// How the closeness of a guess to the target is measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DistanceMode {
+    #[default]
+    Map,
+    Stops,
+    StopsAndChanges,
+    Geographic
+}
+
+impl DistanceMode {
+    pub const ALL: [DistanceMode; 4] = [
+        DistanceMode::Map,
+        DistanceMode::Stops,
+        DistanceMode::StopsAndChanges,
+        DistanceMode::Geographic
+    ];
+}
+
+impl Display for DistanceMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DistanceMode::Map => write!(f, "Map Distance"),
+            DistanceMode::Stops => write!(f, "Stops"),
+            DistanceMode::StopsAndChanges => write!(f, "Stops + Changes"),
+            DistanceMode::Geographic => write!(f, "Real Distance")
+        }
+    }
+}
+
+/// Whether the player is told how the target's zone compares to their guess.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ZoneHints {
+    #[default]
+    Off,
+    Free,
+    // Costs the same as the zone hint in the hints menu, which it also reveals
+    Paid
+}
+
+impl ZoneHints {
+    pub const ALL: [ZoneHints; 3] = [
+        ZoneHints::Off,
+        ZoneHints::Free,
+        ZoneHints::Paid
+    ];
+}
+
+impl Display for ZoneHints {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ZoneHints::Off => write!(f, "No Zone Hints"),
+            ZoneHints::Free => write!(f, "Free Zone Hints"),
+            ZoneHints::Paid => write!(f, "Paid Zone Hints")
+        }
+    }
+}
+
+/// Which rail networks the target can be picked from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetworkFilter {
+    #[default]
+    UndergroundOnly,
+    AllRail
+}
+
+impl NetworkFilter {
+    pub const ALL: [NetworkFilter; 2] = [
+        NetworkFilter::UndergroundOnly,
+        NetworkFilter::AllRail
+    ];
+
+    pub fn allows(&self, network: RailNetwork) -> bool {
+        match self {
+            NetworkFilter::UndergroundOnly => network == RailNetwork::Underground,
+            NetworkFilter::AllRail => true
+        }
+    }
+}
+
+impl Display for NetworkFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkFilter::UndergroundOnly => write!(f, "Underground Only"),
+            NetworkFilter::AllRail => write!(f, "All Rail")
+        }
+    }
+}
+
+/// Whether each guess points towards the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DirectionHints {
+    #[default]
+    Off,
+    Arrows
+}
+
+impl DirectionHints {
+    pub const ALL: [DirectionHints; 2] = [
+        DirectionHints::Off,
+        DirectionHints::Arrows
+    ];
+}
+
+impl Display for DirectionHints {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DirectionHints::Off => write!(f, "No Direction Hints"),
+            DirectionHints::Arrows => write!(f, "Direction Arrows")
+        }
+    }
+}
+
+/// How guesses on the map show their distance to the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MarkerStyle {
+    #[default]
+    Coloured,
+    // A ring through the target is drawn around each guess
+    Rings
+}
+
+impl MarkerStyle {
+    pub const ALL: [MarkerStyle; 2] = [
+        MarkerStyle::Coloured,
+        MarkerStyle::Rings
+    ];
+}
+
+impl Display for MarkerStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MarkerStyle::Coloured => write!(f, "Coloured Markers"),
+            MarkerStyle::Rings => write!(f, "Distance Rings")
+        }
+    }
+}
+
+/// Whether the map image is shown behind the guesses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapVisibility {
+    #[default]
+    Shown,
+    // Only the guessed stations are drawn
+    Blind
+}
+
+impl MapVisibility {
+    pub const ALL: [MapVisibility; 2] = [
+        MapVisibility::Shown,
+        MapVisibility::Blind
+    ];
+}
+
+impl Display for MapVisibility {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapVisibility::Shown => write!(f, "Map Shown"),
+            MapVisibility::Blind => write!(f, "Blind Mode")
+        }
+    }
+}
+
+/// How many guesses the player gets before the game is lost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GuessLimit {
+    #[default]
+    Unlimited,
+    Limited(usize)
+}
+
+impl GuessLimit {
+    pub const ALL: [GuessLimit; 5] = [
+        GuessLimit::Unlimited,
+        GuessLimit::Limited(6),
+        GuessLimit::Limited(10),
+        GuessLimit::Limited(15),
+        GuessLimit::Limited(20)
+    ];
+
+    pub fn max(&self) -> Option<usize> {
+        match self {
+            GuessLimit::Unlimited => None,
+            GuessLimit::Limited(max) => Some(*max)
+        }
+    }
+}
+
+impl Display for GuessLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GuessLimit::Unlimited => write!(f, "Unlimited Guesses"),
+            GuessLimit::Limited(max) => write!(f, "{} Guesses", max)
+        }
+    }
+}
+
+/// Whether the game is timed, and if it has to be finished in time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimerMode {
+    #[default]
+    Off,
+    Stopwatch,
+    // The game is lost when this many seconds have passed
+    Countdown(u64)
+}
+
+impl TimerMode {
+    pub const ALL: [TimerMode; 5] = [
+        TimerMode::Off,
+        TimerMode::Stopwatch,
+        TimerMode::Countdown(60),
+        TimerMode::Countdown(120),
+        TimerMode::Countdown(300)
+    ];
+
+    pub fn limit(&self) -> Option<Duration> {
+        match self {
+            TimerMode::Countdown(secs) => Some(Duration::from_secs(*secs)),
+            TimerMode::Off | TimerMode::Stopwatch => None
+        }
+    }
+}
+
+impl Display for TimerMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimerMode::Off => write!(f, "No Timer"),
+            TimerMode::Stopwatch => write!(f, "Stopwatch"),
+            TimerMode::Countdown(secs) => write!(f, "{} Minute Countdown", secs / 60)
+        }
+    }
+}
+
+/// Restricts which stations can be the target, and optionally which can be guessed.
+/// No lines or no zones picked means stations aren't restricted by them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TargetPool {
+    // Bit n is set if LineId(n) is picked
+    pub lines: u32,
+    // Bit n is set if zone n + 1 is picked
+    pub zones: u16,
+    pub guesses_too: bool
+}
+
+impl TargetPool {
+    pub const MAX_LINES: usize = 32;
+    pub const MAX_ZONE: u8 = 9;
+
+    pub fn has_line(&self, line: LineId) -> bool {
+        line.0 < Self::MAX_LINES && self.lines & (1 << line.0) != 0
+    }
+
+    pub fn set_line(&mut self, line: LineId, picked: bool) {
+        if line.0 >= Self::MAX_LINES {
+            return
+        }
+        if picked {
+            self.lines |= 1 << line.0;
+        } else {
+            self.lines &= !(1 << line.0);
+        }
+    }
+
+    pub fn has_zone(&self, zone: u8) -> bool {
+        (1..=Self::MAX_ZONE).contains(&zone) && self.zones & (1 << (zone - 1)) != 0
+    }
+
+    pub fn set_zone(&mut self, zone: u8, picked: bool) {
+        if !(1..=Self::MAX_ZONE).contains(&zone) {
+            return
+        }
+        if picked {
+            self.zones |= 1 << (zone - 1);
+        } else {
+            self.zones &= !(1 << (zone - 1));
+        }
+    }
+
+    pub fn allows(&self, station: &Station) -> bool {
+        let on_line = self.lines == 0 || station.lines.iter().any(|line| self.has_line(*line));
+        let in_zone = self.zones == 0 || station.zones.iter().any(|zone| self.has_zone(*zone));
+        on_line && in_zone
+    }
+}
+
+/// The rules a game is played under, these are shared through the game code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GameOptions {
+    pub distance_mode: DistanceMode,
+    pub zone_hints: ZoneHints,
+    pub networks: NetworkFilter,
+    pub map: MapVisibility,
+    pub pool: TargetPool,
+    pub guess_limit: GuessLimit,
+    pub timer: TimerMode,
+    pub direction_hints: DirectionHints,
+    pub markers: MarkerStyle
+}
+
+// The game code is a mixed radix number, each field is
+// stored as a digit, and then the whole thing is scrambled.
+const CODE_MASK: u64 = 0b0011010100101;
+const STATION_RADIX: usize = 1234;
+const SALT_RADIX: usize = 1000;
+
+impl GameOptions {
+    /// Creates a shareable game code for the given mode, target and these options.
+    /// Returns `None` if the mode can't be shared or there are too many lines for the code to fit.
+    pub fn encode(&self, mode: GameMode, target: usize, num_lines: usize) -> Option<u64> {
+        let mode_idx = GameMode::SHARED.iter().position(|shared| *shared == mode)?;
+        let mut writer = CodeWriter { value: 0, radix: Some(1) };
+        // The salt makes codes for the same game look different
+        let salt = rand::thread_rng().gen_range(0..SALT_RADIX);
+        writer.push(salt, SALT_RADIX);
+        writer.push(target, STATION_RADIX);
+        writer.push(mode_idx, GameMode::SHARED.len());
+        writer.push(Self::index_of(&DistanceMode::ALL, self.distance_mode), DistanceMode::ALL.len());
+        writer.push(Self::index_of(&ZoneHints::ALL, self.zone_hints), ZoneHints::ALL.len());
+        writer.push(Self::index_of(&NetworkFilter::ALL, self.networks), NetworkFilter::ALL.len());
+        writer.push(Self::index_of(&MapVisibility::ALL, self.map), MapVisibility::ALL.len());
+        writer.push(Self::index_of(&GuessLimit::ALL, self.guess_limit), GuessLimit::ALL.len());
+        writer.push(Self::index_of(&TimerMode::ALL, self.timer), TimerMode::ALL.len());
+        writer.push(Self::index_of(&DirectionHints::ALL, self.direction_hints), DirectionHints::ALL.len());
+        writer.push(Self::index_of(&MarkerStyle::ALL, self.markers), MarkerStyle::ALL.len());
+        // The pool goes last, so that codes without one don't need its digits
+        writer.push(self.pool.guesses_too as usize, 2);
+        writer.push(self.pool.zones as usize, 1 << TargetPool::MAX_ZONE);
+        writer.push(self.pool.lines as usize, Self::line_radix(num_lines));
+        writer.radix.map(|_| writer.value ^ CODE_MASK)
+    }
+
+    /// Reads the mode, target and options back out of a game code.
+    /// Returns `None` if the code doesn't describe a valid game.
+    pub fn decode(code: u64, num_stations: usize, num_lines: usize) -> Option<(usize, GameMode, GameOptions)> {
+        let mut reader = CodeReader { value: code ^ CODE_MASK };
+        reader.pop(SALT_RADIX);
+        let target = reader.pop(STATION_RADIX);
+        let mode = GameMode::SHARED[reader.pop(GameMode::SHARED.len())];
+        let distance_mode = DistanceMode::ALL[reader.pop(DistanceMode::ALL.len())];
+        let zone_hints = ZoneHints::ALL[reader.pop(ZoneHints::ALL.len())];
+        let networks = NetworkFilter::ALL[reader.pop(NetworkFilter::ALL.len())];
+        let map = MapVisibility::ALL[reader.pop(MapVisibility::ALL.len())];
+        let guess_limit = GuessLimit::ALL[reader.pop(GuessLimit::ALL.len())];
+        let timer = TimerMode::ALL[reader.pop(TimerMode::ALL.len())];
+        let direction_hints = DirectionHints::ALL[reader.pop(DirectionHints::ALL.len())];
+        let markers = MarkerStyle::ALL[reader.pop(MarkerStyle::ALL.len())];
+        let guesses_too = reader.pop(2) == 1;
+        let zones = reader.pop(1 << TargetPool::MAX_ZONE) as u16;
+        let lines = reader.pop(Self::line_radix(num_lines)) as u32;
+        let pool = TargetPool { lines, zones, guesses_too };
+
+        // Anything left over wasn't written by `encode`
+        if reader.value != 0 || target >= num_stations {
+            return None
+        }
+        Some((target, mode, GameOptions { distance_mode, zone_hints, networks, map, pool, guess_limit, timer, direction_hints, markers }))
+    }
+
+    // Only as many bits as there are lines are needed for the pool
+    fn line_radix(num_lines: usize) -> usize {
+        1 << num_lines.min(TargetPool::MAX_LINES)
+    }
+
+    fn index_of<T: PartialEq>(all: &[T], value: T) -> usize {
+        all.iter().position(|other| *other == value).unwrap_or(0)
+    }
+}
+
+struct CodeWriter {
+    value: u64,
+    // `None` once every possible code no longer fits
+    radix: Option<u64>
+}
+
+impl CodeWriter {
+    fn push(&mut self, digit: usize, base: usize) {
+        if let Some(radix) = self.radix {
+            self.radix = radix.checked_mul(base as u64);
+            // The value is always below the radix, so it fits whenever the radix does
+            if self.radix.is_some() {
+                self.value += digit as u64 * radix;
+            }
+        }
+    }
+}
+
+struct CodeReader {
+    value: u64
+}
+
+impl CodeReader {
+    fn pop(&mut self, base: usize) -> usize {
+        let digit = self.value % base as u64;
+        self.value /= base as u64;
+        digit as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NUM_STATIONS: usize = 300;
+    const NUM_LINES: usize = 20;
+
+    fn last<T: Copy>(all: &[T]) -> T {
+        all[all.len() - 1]
+    }
+
+    fn maximum_options(num_lines: usize) -> GameOptions {
+        GameOptions {
+            distance_mode: last(&DistanceMode::ALL),
+            zone_hints: last(&ZoneHints::ALL),
+            networks: last(&NetworkFilter::ALL),
+            map: last(&MapVisibility::ALL),
+            pool: TargetPool {
+                lines: (GameOptions::line_radix(num_lines) - 1) as u32,
+                zones: (1 << TargetPool::MAX_ZONE) - 1,
+                guesses_too: true
+            },
+            guess_limit: last(&GuessLimit::ALL),
+            timer: last(&TimerMode::ALL),
+            direction_hints: last(&DirectionHints::ALL),
+            markers: last(&MarkerStyle::ALL)
+        }
+    }
+
+    #[test]
+    fn round_trips_default_options() {
+        let options = GameOptions::default();
+        let code = options.encode(GameMode::FindTarget, 0, NUM_LINES).unwrap();
+        assert_eq!(GameOptions::decode(code, NUM_STATIONS, NUM_LINES), Some((0, GameMode::FindTarget, options)));
+    }
+
+    #[test]
+    fn round_trips_maximum_options() {
+        let options = maximum_options(NUM_LINES);
+        let target = NUM_STATIONS - 1;
+        let mode = last(&GameMode::SHARED);
+        // The salt is random, so try a few different codes
+        for _ in 0..50 {
+            let code = options.encode(mode, target, NUM_LINES).unwrap();
+            assert_eq!(GameOptions::decode(code, NUM_STATIONS, NUM_LINES), Some((target, mode, options)));
+        }
+    }
+
+    #[test]
+    fn rejects_codes_for_missing_stations() {
+        let code = GameOptions::default().encode(GameMode::FindTarget, NUM_STATIONS, NUM_LINES).unwrap();
+        assert_eq!(GameOptions::decode(code, NUM_STATIONS, NUM_LINES), None);
+    }
+
+    #[test]
+    fn default_codes_are_short() {
+        for mode in GameMode::SHARED {
+            let code = GameOptions::default().encode(mode, NUM_STATIONS - 1, NUM_LINES).unwrap();
+            assert!(code < 10_000_000, "{} is too long", code);
+        }
+    }
+
+    #[test]
+    fn rejects_codes_with_digits_left_over() {
+        // A code made with more lines than there are now
+        let code = maximum_options(NUM_LINES).encode(GameMode::FindTarget, 0, NUM_LINES).unwrap();
+        assert_eq!(GameOptions::decode(code, NUM_STATIONS, NUM_LINES - 1), None);
+        assert_eq!(GameOptions::decode(u64::MAX, NUM_STATIONS, NUM_LINES), None);
+    }
+
+    #[test]
+    fn too_many_lines_do_not_fit() {
+        let num_lines = TargetPool::MAX_LINES;
+        assert_eq!(GameOptions::default().encode(GameMode::FindTarget, 0, num_lines), None);
+        assert_eq!(maximum_options(num_lines).encode(GameMode::FindTarget, 0, num_lines), None);
+    }
+
+    #[test]
+    fn only_shared_modes_have_codes() {
+        for mode in GameMode::ALL {
+            let code = GameOptions::default().encode(mode, 0, NUM_LINES);
+            assert_eq!(code.is_some(), GameMode::SHARED.contains(&mode));
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// Where the current game is up to, this decides which messages are accepted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GamePhase {
+    // A target has been picked but nothing has been guessed,
+    // the options can only be changed in this phase
+    #[default]
+    NotStarted,
+    Playing,
+    Won,
+    GaveUp,
+    // Used every guess in a game with a guess limit
+    OutOfGuesses,
+    // The countdown ran out
+    TimedOut
+}
+
+impl GamePhase {
+    pub fn is_over(&self) -> bool {
+        matches!(self, GamePhase::Won | GamePhase::GaveUp | GamePhase::OutOfGuesses | GamePhase::TimedOut)
+    }
+
+    /// Whether the game ended without finding the target, so it should be revealed.
+    pub fn is_lost(&self) -> bool {
+        matches!(self, GamePhase::GaveUp | GamePhase::OutOfGuesses | GamePhase::TimedOut)
+    }
+}
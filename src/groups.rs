@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use serde::Deserialize;
+use crate::stations::Station;
+
+/// A group of stations that share a name, as described in `station_groups.json5`.
+#[derive(Debug, Deserialize)]
+pub struct GroupData {
+    pub name: String,
+    pub stations: Vec<String>
+}
+
+#[derive(Debug)]
+pub enum GroupError {
+    UnknownStation { group: String, station: String },
+    DuplicateStation { station: String }
+}
+
+impl Display for GroupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupError::UnknownStation { group, station } => {
+                write!(f, "{} group references unknown station '{}'", group, station)
+            }
+            GroupError::DuplicateStation { station } => {
+                write!(f, "'{}' is in more than one group", station)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct StationGroup {
+    pub name: String,
+    pub members: Vec<usize>
+}
+
+/// Every station belongs to exactly one group, stations
+/// that aren't in a defined group are in a group on their own.
+#[derive(Debug, Default)]
+pub struct StationGroups {
+    groups: Vec<StationGroup>,
+    group_of: Vec<usize>
+}
+
+impl StationGroups {
+    pub fn new(stations: &[Station], data: Vec<GroupData>) -> Result<Self, Vec<GroupError>> {
+        let mut errors = vec![];
+        let indices: HashMap<&str, usize> = stations.iter()
+            .enumerate()
+            .map(|(idx, station)| (station.name.as_str(), idx))
+            .collect();
+
+        let mut groups = vec![];
+        let mut group_of: Vec<Option<usize>> = vec![None; stations.len()];
+        for group in data {
+            let mut members = vec![];
+            for name in &group.stations {
+                let Some(&station_idx) = indices.get(name.as_str()) else {
+                    errors.push(GroupError::UnknownStation {
+                        group: group.name.clone(),
+                        station: name.clone()
+                    });
+                    continue
+                };
+                if group_of[station_idx].is_some() {
+                    errors.push(GroupError::DuplicateStation { station: name.clone() });
+                    continue
+                }
+                group_of[station_idx] = Some(groups.len());
+                members.push(station_idx);
+            }
+            groups.push(StationGroup { name: group.name, members });
+        }
+
+        if !errors.is_empty() {
+            return Err(errors)
+        }
+
+        // Any remaining stations are in a group by themselves
+        let group_of = group_of.iter().enumerate().map(|(station_idx, group_idx)| {
+            group_idx.unwrap_or_else(|| {
+                groups.push(StationGroup {
+                    name: stations[station_idx].name.clone(),
+                    members: vec![station_idx]
+                });
+                groups.len() - 1
+            })
+        }).collect();
+        Ok(Self { groups, group_of })
+    }
+
+    /// The group the station belongs to.
+    pub fn group(&self, station_idx: usize) -> &StationGroup {
+        &self.groups[self.group_of[station_idx]]
+    }
+
+    pub fn same_group(&self, first_idx: usize, second_idx: usize) -> bool {
+        self.group_of[first_idx] == self.group_of[second_idx]
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt::{Display, Formatter};
+
+/// How the guesses in the side panel are sorted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GuessOrder {
+    #[default]
+    Guessed,
+    Closeness
+}
+
+impl GuessOrder {
+    pub const ALL: [GuessOrder; 2] = [
+        GuessOrder::Guessed,
+        GuessOrder::Closeness
+    ];
+}
+
+impl Display for GuessOrder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GuessOrder::Guessed => write!(f, "Guess Order"),
+            GuessOrder::Closeness => write!(f, "Closest First")
+        }
+    }
+}
+
+/// Every station guessed this game, in the order they were guessed.
+#[derive(Debug, Default)]
+pub struct GuessLog {
+    guesses: Vec<usize>
+}
+
+impl GuessLog {
+    pub fn push(&mut self, station_idx: usize) {
+        self.guesses.push(station_idx)
+    }
+
+    pub fn clear(&mut self) {
+        self.guesses.clear()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.guesses.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.guesses.iter().copied()
+    }
+
+    /// Each guess's number (starting at 1) and station, in the given order.
+    pub fn ordered(&self, order: GuessOrder, closeness: impl Fn(usize) -> f32) -> Vec<(usize, usize)> {
+        let mut ordered: Vec<(usize, usize)> = self.guesses.iter()
+            .enumerate()
+            .map(|(idx, station_idx)| (idx + 1, *station_idx))
+            .collect();
+        if order == GuessOrder::Closeness {
+            // Stable, so equally close guesses stay in the order they were made
+            ordered.sort_by(|(_, first_idx), (_, second_idx)| {
+                closeness(*second_idx).total_cmp(&closeness(*first_idx))
+            });
+        }
+        ordered
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+
+/// Something about the target that a stuck player can pay to reveal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Hint {
+    WordCount,
+    Zone,
+    Line,
+    // A shaded area of the map that the target is somewhere in
+    Region,
+    FirstLetter
+}
+
+impl Hint {
+    // From weakest to strongest
+    pub const ALL: [Hint; 5] = [
+        Hint::WordCount,
+        Hint::Zone,
+        Hint::Line,
+        Hint::Region,
+        Hint::FirstLetter
+    ];
+
+    /// How many guesses revealing the hint counts as.
+    pub fn cost(&self) -> usize {
+        match self {
+            Hint::WordCount | Hint::Zone => 1,
+            Hint::Line | Hint::Region => 2,
+            Hint::FirstLetter => 3
+        }
+    }
+}
+
+impl Display for Hint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hint::WordCount => write!(f, "Number of Words"),
+            Hint::Zone => write!(f, "Zone"),
+            Hint::Line => write!(f, "A Line"),
+            Hint::Region => write!(f, "Map Region"),
+            Hint::FirstLetter => write!(f, "First Letter")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stronger_hints_cost_more() {
+        for pair in Hint::ALL.windows(2) {
+            assert!(pair[0].cost() <= pair[1].cost(), "{} costs more than {}", pair[0], pair[1]);
+        }
+        assert!(Hint::ALL.iter().all(|hint| hint.cost() > 0));
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use iced::Color;
+use serde::{Deserialize, Deserializer};
+use serde::de::Error;
+use crate::stations::Station;
+
+/// Index of a line in the [`LineRegistry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineId(pub usize);
+
+/// A single line, as described in `lines.json5`.
+/// Each branch is a list of station names where neighbouring
+/// names in the list are directly connected by the line.
+#[derive(Debug, Deserialize)]
+pub struct Line {
+    pub id: String,
+    pub name: String,
+    #[serde(deserialize_with = "deserialize_colour")]
+    pub colour: Color,
+    pub network: RailNetwork,
+    pub branches: Vec<Vec<String>>
+}
+
+/// The rail network a line belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum RailNetwork {
+    Underground,
+    #[serde(rename = "DLR")]
+    Dlr,
+    Overground,
+    Elizabeth,
+    Tram
+}
+
+impl RailNetwork {
+    pub const ALL: [RailNetwork; 5] = [
+        RailNetwork::Underground,
+        RailNetwork::Dlr,
+        RailNetwork::Overground,
+        RailNetwork::Elizabeth,
+        RailNetwork::Tram
+    ];
+}
+
+impl Display for RailNetwork {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RailNetwork::Underground => write!(f, "Underground"),
+            RailNetwork::Dlr => write!(f, "DLR"),
+            RailNetwork::Overground => write!(f, "Overground"),
+            RailNetwork::Elizabeth => write!(f, "Elizabeth line"),
+            RailNetwork::Tram => write!(f, "Tram")
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LineError {
+    UnknownLine { station: String, line: String },
+    DuplicateLine { line: String }
+}
+
+impl Display for LineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineError::UnknownLine { station, line } => {
+                write!(f, "'{}' lists unknown line '{}'", station, line)
+            }
+            LineError::DuplicateLine { line } => {
+                write!(f, "line '{}' is defined more than once", line)
+            }
+        }
+    }
+}
+
+/// Every line loaded from the data, looked up by [`LineId`].
+#[derive(Debug, Default)]
+pub struct LineRegistry {
+    lines: Vec<Line>,
+    ids: HashMap<String, LineId>
+}
+
+impl LineRegistry {
+    pub fn new(lines: Vec<Line>) -> Result<Self, Vec<LineError>> {
+        let mut errors = vec![];
+        let mut ids = HashMap::new();
+        for (idx, line) in lines.iter().enumerate() {
+            if ids.insert(line.id.clone(), LineId(idx)).is_some() {
+                errors.push(LineError::DuplicateLine { line: line.id.clone() });
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors)
+        }
+        Ok(Self { lines, ids })
+    }
+
+    /// Resolves the line ids each station lists into [`LineId`]s.
+    pub fn resolve(&self, stations: &mut [Station]) -> Result<(), Vec<LineError>> {
+        let mut errors = vec![];
+        for station in stations.iter_mut() {
+            let mut lines = vec![];
+            for line in &station.line_ids {
+                match self.ids.get(line) {
+                    Some(id) => lines.push(*id),
+                    None => errors.push(LineError::UnknownLine {
+                        station: station.name.clone(),
+                        line: line.clone()
+                    })
+                }
+            }
+            station.lines = lines;
+        }
+
+        if !errors.is_empty() {
+            return Err(errors)
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, id: LineId) -> &Line {
+        &self.lines[id.0]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LineId, &Line)> {
+        self.lines.iter().enumerate().map(|(idx, line)| (LineId(idx), line))
+    }
+}
+
+fn deserialize_colour<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    let digits = hex.strip_prefix('#').unwrap_or(&hex);
+    if digits.len() != 6 {
+        return Err(D::Error::custom(format!("invalid colour '{}'", hex)))
+    }
+
+    let rgb = u32::from_str_radix(digits, 16)
+        .map_err(|_| D::Error::custom(format!("invalid colour '{}'", hex)))?;
+    Ok(Color::from_rgb8((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
@@ -1,36 +1,66 @@
 mod render_overlay;
+mod map_viewer;
 mod stations;
 mod coordinate_system;
 mod resource_util;
-
+mod network;
+mod lines;
+mod search;
+mod groups;
+mod game_options;
+mod distance;
+mod guess_log;
+mod game_phase;
+mod completion;
+mod timer;
+mod daily;
+mod hints;
+
+use std::any::Any;
 use std::collections::HashSet;
 use std::fs;
 use std::fs::File;
-use std::ops::{Add, Deref};
+use std::ops::Add;
 use std::time::{Duration, Instant};
-use iced::{Color, executor, Font, font, Pixels, Point, Rectangle, Renderer, Size, Vector};
-use iced::widget::{container, row, image, text_input, Column, canvas, button, text};
-use iced::{Application, Command, Element, Length, Settings, Theme};
+use iced::{Border, Color, executor, Font, font, Pixels, Point, Rectangle, Renderer, Size, Vector};
+use iced::widget::{container, row, column, image, text_input, Column, Row, canvas, button, text, pick_list, scrollable, Space, checkbox, progress_bar, tooltip};
+use iced::advanced::widget::{Id, Operation};
+use iced::advanced::widget::tree::Tag;
+use iced::{Application, Command, Element, Length, Settings, Subscription, Theme, theme, keyboard, time};
 use iced::alignment::{Horizontal, Vertical};
 use iced::font::{Family, Weight};
+use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
 use iced::mouse::Cursor;
-use iced::widget::canvas::{Cache, Geometry, Path, Program, Text};
-use iced::widget::image::viewer;
+use iced::widget::canvas::{Cache, Frame, Geometry, Path, Program, Stroke, Text};
 use iced_aw::modal;
+use iced_aw::DropDown;
 use iced_aw::native::Card;
 use json_comments::StripComments;
-use simsearch::{SearchOptions, SimSearch};
-use regex::Regex;
-use rand::{random, Rng};
-use rand::seq::SliceRandom;
-use crate::render_overlay::RenderOverlay;
+use rand::Rng;
+use crate::render_overlay::{OverlaidState, RenderOverlay};
+use crate::map_viewer::{MapViewer, ViewerState};
 use crate::stations::Station;
 use crate::coordinate_system::CoordinateSystem;
 use crate::resource_util::convert_relative_path;
+use crate::network::Network;
+use crate::lines::{Line, LineId, LineRegistry, RailNetwork};
+use crate::search::StationSearch;
+use crate::groups::{GroupData, StationGroups};
+use crate::game_options::{DirectionHints, DistanceMode, GameMode, GameOptions, GuessLimit, MapVisibility, MarkerStyle, NetworkFilter, TargetPool, TimerMode, ZoneHints};
+use crate::distance::{DistanceMetric, geo_direction, GeoDistance, haversine_km, MapDistance, StopDistance};
+use crate::guess_log::{GuessLog, GuessOrder};
+use crate::game_phase::GamePhase;
+use crate::completion::CompletionSave;
+use crate::timer::{format_duration, Stopwatch};
+use crate::daily::{daily_index, dataset_version, DailyResult, DailyStats};
+use crate::hints::Hint;
 
 pub fn main() -> iced::Result {
-    let mut settings = Settings::default();
-    settings.antialiasing = true;
+    let settings = Settings {
+        antialiasing: true,
+        ..Settings::default()
+    };
     TubeTagApp::run(settings)
 }
 
@@ -48,7 +78,7 @@ impl Title {
         overlay: Option<Color>,
         duration: Duration
     ) -> Self {
-        return Self {
+        Self {
             message: Text {
                 content: message.to_string(),
                 color,
@@ -67,17 +97,53 @@ impl Title {
 struct TubeTagApp {
     // Backend
     all_stations: Vec<Station>,
+    lines: LineRegistry,
+    groups: StationGroups,
+    network: Network,
     guessed_stations: HashSet<usize>,
+    guess_log: GuessLog,
     target_station: Option<usize>,
-    search_engine: SimSearch<usize>,
+    metric: Option<Box<dyn DistanceMetric>>,
+    last_guess: Option<usize>,
+    // Hints revealed this game in the order they were used,
+    // and where the map region hint is centred
+    hints_used: Vec<Hint>,
+    hint_region: Option<(f32, f32)>,
+    options: GameOptions,
+    mode: GameMode,
+    stopwatch: Stopwatch,
+    dataset_version: u64,
+    daily_stats: DailyStats,
+    // The day of the daily puzzle being played, and whether this attempt counts
+    daily_day: Option<u64>,
+    daily_counts: bool,
+    // Why the last save failed, if it did
+    save_error: Option<String>,
+    search_engine: StationSearch,
     num_guesses: usize,
+    phase: GamePhase,
+    confirming_give_up: bool,
+    confirming_restart: bool,
+    show_summary: bool,
+    show_setup: bool,
+    show_hints: bool,
     show_modal: bool,
-    game_code: usize,
+    // `None` if there's no target, or the options don't fit in a code
+    game_code: Option<u64>,
+    // Stations the last guess could have meant, waiting for the player to pick one
+    candidates: Vec<usize>,
+    selected_candidate: usize,
+    // Stations matching the input so far
+    suggestions: Vec<usize>,
+    selected_suggestion: Option<usize>,
 
     // Frontend
     station_input: String,
     render_cache: Cache,
     title: Option<Title>,
+    // Station whose markers are flashing and when they started
+    flash: Option<(usize, Instant)>,
+    guess_order: GuessOrder,
     game_code_input: String,
 
     viewing_map: bool
@@ -89,12 +155,39 @@ enum Message {
     GuessInputChanged(String),
     GuessSubmitted,
     Restart,
+    RestartConfirmed,
     GiveUp,
+    GiveUpConfirmed,
     ShowMap,
     PlayAlong,
     CloseModal,
     GameCodeInputChanged(String),
-    GameCodeSubmitted
+    GameCodeSubmitted,
+    DistanceModeSelected(DistanceMode),
+    ZoneHintsSelected(ZoneHints),
+    NetworkFilterSelected(NetworkFilter),
+    DirectionHintsSelected(DirectionHints),
+    MarkerStyleSelected(MarkerStyle),
+    ShowSetup,
+    PoolLineToggled(LineId, bool),
+    PoolZoneToggled(u8, bool),
+    PoolGuessesToggled(bool),
+    MapVisibilitySelected(MapVisibility),
+    GuessLimitSelected(GuessLimit),
+    GameModeSelected(GameMode),
+    TimerToggled(bool),
+    TimerModeSelected(TimerMode),
+    Tick,
+    ShowHints,
+    HintRevealed(Hint),
+    CandidateMoved(isize),
+    CandidatePicked(usize),
+    CandidateConfirmed,
+    SuggestionMoved(isize),
+    SuggestionCompleted,
+    SuggestionPicked(usize),
+    GuessOrderSelected(GuessOrder),
+    GuessLogSelected(usize)
 }
 
 impl Application for TubeTagApp {
@@ -108,23 +201,44 @@ impl Application for TubeTagApp {
         let station_locations_path = convert_relative_path("assets/station_locations.json5");
         let station_locations_file = File::open(station_locations_path)
             .expect("Missing station_locations.json5");
-        let stations: Vec<Station> = serde_json::from_reader(StripComments::new(station_locations_file))
+        let mut stations: Vec<Station> = serde_json::from_reader(StripComments::new(station_locations_file))
             .expect("station_locations.json5 was invalid");
 
+        // Load lines and resolve the lines of each station
+        let lines_path = convert_relative_path("assets/lines.json5");
+        let lines_file = File::open(lines_path)
+            .expect("Missing lines.json5");
+        let lines: Vec<Line> = serde_json::from_reader(StripComments::new(lines_file))
+            .expect("lines.json5 was invalid");
+        let lines = LineRegistry::new(lines)
+            .and_then(|lines| lines.resolve(&mut stations).map(|_| lines))
+            .unwrap_or_else(|errors| {
+                let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                panic!("Invalid lines:\n{}", messages.join("\n"))
+            });
+
+        // Load groups of stations that share a name
+        let groups_path = convert_relative_path("assets/station_groups.json5");
+        let groups_file = File::open(groups_path)
+            .expect("Missing station_groups.json5");
+        let groups: Vec<GroupData> = serde_json::from_reader(StripComments::new(groups_file))
+            .expect("station_groups.json5 was invalid");
+        let groups = StationGroups::new(&stations, groups).unwrap_or_else(|errors| {
+            let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+            panic!("Invalid station groups:\n{}", messages.join("\n"))
+        });
+
+        // Build the network from the line sequences
+        let network = Network::new(&stations, &lines).unwrap_or_else(|errors| {
+            let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+            panic!("lines.json5 has broken links:\n{}", messages.join("\n"))
+        });
+
+        // Daily puzzles change when the stations do
+        let version = dataset_version(&stations);
+
         // Initialize search engine
-        let mut search_engine = SimSearch::new_with(
-            SearchOptions::new().threshold(0.75).stop_whitespace(false).levenshtein(true)
-        );
-        let regex = Regex::new(r"\((.*?)\)").unwrap();
-        for station_idx in 0..stations.len() {
-            let name = &stations[station_idx].name;
-            if regex.is_match(name) {
-                // We insert the name without brackets too
-                search_engine.insert_tokens(station_idx, &[name, &regex.replace_all(name, "")]);
-            } else {
-                search_engine.insert(station_idx, name);
-            }
-        }
+        let search_engine = StationSearch::new(&stations, &groups);
 
         // Create a command to load the font
         let font_filepath = convert_relative_path("fonts/P22UndergroundPro-Bold.ttf");
@@ -133,15 +247,43 @@ impl Application for TubeTagApp {
         // Construct a TubeTagApp object
         let mut ret = Self {
             all_stations: stations,
+            lines,
+            groups,
+            network,
             guessed_stations: HashSet::new(),
+            guess_log: GuessLog::default(),
             target_station: None,
+            metric: None,
+            last_guess: None,
+            hints_used: vec![],
+            hint_region: None,
+            options: GameOptions::default(),
+            mode: GameMode::default(),
+            stopwatch: Stopwatch::default(),
+            dataset_version: version,
+            daily_stats: DailyStats::load(),
+            daily_day: None,
+            daily_counts: false,
+            save_error: None,
             search_engine,
             num_guesses: 0,
-            game_code: 0,
+            phase: GamePhase::default(),
+            confirming_give_up: false,
+            confirming_restart: false,
+            show_summary: false,
+            show_setup: false,
+            show_hints: false,
+            game_code: None,
             show_modal: false,
+            candidates: vec![],
+            selected_candidate: 0,
+            suggestions: vec![],
+            selected_suggestion: None,
             station_input: String::new(),
             render_cache: Cache::new(),
             title: None,
+            flash: None,
+            guess_order: GuessOrder::default(),
             viewing_map: false,
             game_code_input: String::new()
         };
@@ -158,20 +300,39 @@ impl Application for TubeTagApp {
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::GuessInputChanged(input) => {
-                self.station_input = input
+                self.station_input = input;
+                self.update_suggestions();
+            }
+            Message::GuessSubmitted if !self.candidates.is_empty() => {
+                return self.candidate_confirmed()
             }
             Message::GuessSubmitted => {
-                self.guess_submitted()
+                // A selected suggestion is exactly the station the player wants
+                return match self.selected_suggestion {
+                    Some(idx) => self.guess_station(self.suggestions[idx]),
+                    None => self.guess_submitted()
+                }
+            }
+            // Restarting throws away any saved progress, so check first
+            Message::Restart if self.mode == GameMode::EveryStation && self.phase == GamePhase::Playing => {
+                self.confirming_restart = true
             }
             Message::Restart => {
                 self.restart_game()
             }
-            Message::GiveUp => {
-                for idx in 0..self.all_stations.len() {
-                    self.guessed_stations.insert(idx);
-                }
+            Message::RestartConfirmed => {
+                self.report_save("progress", CompletionSave::delete());
+                self.restart_game()
             }
-            Message::ShowMap => {
+            // Make sure the player didn't press Give Up by accident
+            Message::GiveUp if !self.phase.is_over() && self.has_game() => {
+                self.confirming_give_up = true
+            }
+            Message::GiveUpConfirmed if !self.phase.is_over() => {
+                self.confirming_give_up = false;
+                return self.lose_game(GamePhase::GaveUp)
+            }
+            Message::ShowMap if !self.is_map_locked() => {
                 self.viewing_map = !self.viewing_map
             }
             Message::PlayAlong => {
@@ -179,70 +340,426 @@ impl Application for TubeTagApp {
             }
             Message::CloseModal => {
                 self.show_modal = false;
+                self.confirming_give_up = false;
+                self.confirming_restart = false;
+                self.show_summary = false;
+                self.show_setup = false;
+                self.show_hints = false;
+                // Backing out of a choice doesn't count as a guess
+                self.candidates.clear();
             }
             Message::GameCodeInputChanged(input) => {
                 self.game_code_input = input
             }
             Message::GameCodeSubmitted => {
+                let decoded = self.game_code_input.parse::<u64>().ok()
+                    .and_then(|code| {
+                        GameOptions::decode(code, self.all_stations.len(), self.lines.iter().count()).map(|game| (code, game))
+                    })
+                    // The options must be able to pick the target, or the code was made up
+                    .filter(|(_, (target, mode, options))| self.pool_under(*mode, options).contains(target));
+                // The code says which mode it was made in
+                if let Some((_, (_, mode, _))) = decoded.filter(|(_, (_, mode, _))| *mode != self.mode) {
+                    self.save_progress();
+                    self.mode = mode;
+                    self.viewing_map = false;
+                }
                 self.restart_game();
 
-                let new_code = self.game_code_input.parse::<usize>();
-                if (new_code.is_ok()) {
-                    self.game_code = new_code.unwrap();
-                    let new_target = (self.game_code ^ 0b0011010100101) % 1234;
-                    if (new_target < self.all_stations.len()) {
-                        self.target_station = Some(new_target);
-                    }
+                if let Some((new_code, (target, _, options))) = decoded {
+                    self.game_code = Some(new_code);
+                    self.options = options;
+                    self.set_target(target);
+                    self.start_clock();
                 }
 
                 self.show_modal = false;
             }
+            // The mode can only be changed before the first guess
+            Message::DistanceModeSelected(mode) if self.phase == GamePhase::NotStarted => {
+                self.options.distance_mode = mode;
+                self.update_metric();
+                // Map distance can only pick targets that are on the map
+                self.target_options_changed();
+            }
+            Message::ShowSetup if self.phase == GamePhase::NotStarted => {
+                self.show_setup = true
+            }
+            Message::PoolLineToggled(line, picked) if self.phase == GamePhase::NotStarted => {
+                self.options.pool.set_line(line, picked);
+                self.target_options_changed();
+            }
+            Message::PoolZoneToggled(zone, picked) if self.phase == GamePhase::NotStarted => {
+                self.options.pool.set_zone(zone, picked);
+                self.target_options_changed();
+            }
+            Message::PoolGuessesToggled(guesses_too) if self.phase == GamePhase::NotStarted => {
+                self.options.pool.guesses_too = guesses_too;
+                self.update_game_code();
+                self.update_suggestions();
+            }
+            Message::ZoneHintsSelected(hints) if self.phase == GamePhase::NotStarted => {
+                self.options.zone_hints = hints;
+                self.update_game_code();
+            }
+            Message::NetworkFilterSelected(networks) if self.phase == GamePhase::NotStarted => {
+                self.options.networks = networks;
+                self.target_options_changed();
+            }
+            Message::DirectionHintsSelected(hints) if self.phase == GamePhase::NotStarted => {
+                self.options.direction_hints = hints;
+                self.update_game_code();
+            }
+            Message::MarkerStyleSelected(markers) if self.phase == GamePhase::NotStarted => {
+                self.options.markers = markers;
+                self.update_game_code();
+            }
+            Message::MapVisibilitySelected(map) if self.phase == GamePhase::NotStarted => {
+                self.options.map = map;
+                self.viewing_map = false;
+                self.update_game_code();
+            }
+            Message::GuessLimitSelected(limit) if self.phase == GamePhase::NotStarted => {
+                self.options.guess_limit = limit;
+                self.update_game_code();
+            }
+            Message::GameModeSelected(mode) if mode != self.mode => {
+                self.save_progress();
+                self.mode = mode;
+                self.viewing_map = false;
+                self.restart_game();
+                if mode == GameMode::EveryStation {
+                    self.resume_progress();
+                }
+            }
+            Message::TimerToggled(timed) if self.phase == GamePhase::NotStarted => {
+                self.options.timer = if timed { TimerMode::Stopwatch } else { TimerMode::Off };
+                self.start_clock();
+                self.update_game_code();
+            }
+            Message::TimerModeSelected(timer) if self.phase == GamePhase::NotStarted => {
+                self.options.timer = timer;
+                self.start_clock();
+                self.update_game_code();
+            }
+            // Other ticks only redraw the time on screen
+            Message::Tick if !self.phase.is_over() && self.time_left() == Some(Duration::ZERO) => {
+                return self.lose_game(GamePhase::TimedOut)
+            }
+            Message::ShowHints if self.phase == GamePhase::Playing && self.target_station.is_some() => {
+                self.show_hints = true
+            }
+            Message::HintRevealed(hint) if self.phase == GamePhase::Playing && self.can_reveal(hint) => {
+                self.reveal_hint(hint)
+            }
+            Message::CandidateMoved(delta) if !self.candidates.is_empty() => {
+                let len = self.candidates.len() as isize;
+                self.selected_candidate = (self.selected_candidate as isize + delta).rem_euclid(len) as usize;
+            }
+            Message::CandidatePicked(idx) if idx < self.candidates.len() => {
+                self.selected_candidate = idx;
+                return self.candidate_confirmed()
+            }
+            Message::CandidateConfirmed if !self.candidates.is_empty() => {
+                return self.candidate_confirmed()
+            }
+            Message::SuggestionMoved(delta) if !self.suggestions.is_empty() => {
+                let len = self.suggestions.len() as isize;
+                self.selected_suggestion = Some(match self.selected_suggestion {
+                    Some(idx) => (idx as isize + delta).rem_euclid(len) as usize,
+                    // Up from nothing selected wraps round to the last suggestion
+                    None if delta < 0 => self.suggestions.len() - 1,
+                    None => 0
+                });
+            }
+            // Fill in the selected suggestion, or the best one if none is selected
+            Message::SuggestionCompleted if !self.suggestions.is_empty() => {
+                let station_idx = self.suggestions[self.selected_suggestion.unwrap_or_default()];
+                self.station_input = self.groups.group(station_idx).name.clone();
+                self.update_suggestions();
+                return text_input::move_cursor_to_end(text_input::Id::new(GUESS_INPUT_ID))
+            }
+            Message::SuggestionPicked(idx) if idx < self.suggestions.len() => {
+                return self.guess_station(self.suggestions[idx])
+            }
+            Message::GuessOrderSelected(order) => {
+                self.guess_order = order
+            }
+            Message::GuessLogSelected(station_idx) => {
+                if let Some(position) = self.all_stations[station_idx].map_position() {
+                    return Command::widget(CentreMap { position, scale: None })
+                }
+            }
 
             _ => { }
         }
         Command::none()
     }
 
-    fn view(&self) -> Element<Message> {
+    fn subscription(&self) -> Subscription<Message> {
+        // Only listen to the keyboard while choosing between stations
+        let keys = if !self.candidates.is_empty() {
+            keyboard::on_key_press(candidate_key)
+        } else if !self.suggestions.is_empty() {
+            keyboard::on_key_press(suggestion_key)
+        } else {
+            Subscription::none()
+        };
+
+        // Keep the time on screen up to date, this is also what ends a countdown
+        let counting_down = self.counting_down();
+        let timing = self.timer() != TimerMode::Off && !self.phase.is_over() && self.has_game();
+        let tick = if timing || counting_down {
+            time::every(Duration::from_secs(1)).map(|_| Message::Tick)
+        } else {
+            Subscription::none()
+        };
+        Subscription::batch([keys, tick])
+    }
+
+    fn view(&self) -> Element<'_, Message> {
         // Construct map viewer
         let map_path = convert_relative_path("assets/tube-map-8k.png");
         let map_handle = image::Handle::from_path(map_path);
-        let map_viewer = image::viewer(map_handle)
+        let map_viewer = MapViewer::new(map_handle)
             .width(Length::Fill)
             // TODO: We have a scale limiter
             //  but we also need to limit the panning?
             .min_scale(1.0);
 
         // Construct input field
-        let guess_input = text_input("Guess a station", &self.station_input)
-            .on_input(Message::GuessInputChanged)
-            .on_submit(Message::GuessSubmitted);
+        let placeholder = if self.has_game() { "Guess a station" } else { "No stations match the setup" };
+        let mut guess_input = text_input(placeholder, &self.station_input)
+            .id(text_input::Id::new(GUESS_INPUT_ID));
+        // No more guesses once the game is over
+        if !self.phase.is_over() && self.has_game() {
+            guess_input = guess_input
+                .on_input(Message::GuessInputChanged)
+                .on_submit(Message::GuessSubmitted);
+        }
 
-        let guesses_text = text(format!("Guesses: {}", self.num_guesses)).size(16);
+        let guesses_text = text(match self.phase {
+            GamePhase::Won => format!("Won in {} guesses", self.num_guesses),
+            GamePhase::GaveUp => format!("Gave up after {} guesses", self.num_guesses),
+            GamePhase::OutOfGuesses => format!("Out of guesses after {}", self.num_guesses),
+            GamePhase::TimedOut => format!("Out of time after {} guesses", self.num_guesses),
+            GamePhase::NotStarted | GamePhase::Playing => match self.guess_limit() {
+                Some(limit) => format!("Guesses: {} / {}", self.num_guesses, limit),
+                None => format!("Guesses: {}", self.num_guesses)
+            }
+        }).size(16);
+        // Once a guess has been made the distance mode is locked in
+        let distance_mode: Element<Message> = if self.phase == GamePhase::NotStarted {
+            pick_list(
+                &DistanceMode::ALL[..],
+                Some(self.options.distance_mode),
+                Message::DistanceModeSelected
+            ).into()
+        } else {
+            text(self.options.distance_mode.to_string()).size(16).into()
+        };
+        let zone_hints: Element<Message> = if self.phase == GamePhase::NotStarted {
+            pick_list(
+                &ZoneHints::ALL[..],
+                Some(self.options.zone_hints),
+                Message::ZoneHintsSelected
+            ).into()
+        } else {
+            text(self.options.zone_hints.to_string()).size(16).into()
+        };
+        let networks: Element<Message> = if self.phase == GamePhase::NotStarted {
+            pick_list(
+                &NetworkFilter::ALL[..],
+                Some(self.options.networks),
+                Message::NetworkFilterSelected
+            ).into()
+        } else {
+            text(self.options.networks.to_string()).size(16).into()
+        };
+        let direction_hints: Element<Message> = if self.phase == GamePhase::NotStarted {
+            pick_list(
+                &DirectionHints::ALL[..],
+                Some(self.options.direction_hints),
+                Message::DirectionHintsSelected
+            ).into()
+        } else {
+            text(self.options.direction_hints.to_string()).size(16).into()
+        };
+        let markers: Element<Message> = if self.phase == GamePhase::NotStarted && self.distance_mode() == DistanceMode::Map {
+            pick_list(
+                &MarkerStyle::ALL[..],
+                Some(self.options.markers),
+                Message::MarkerStyleSelected
+            ).into()
+        } else {
+            text(self.markers().to_string()).size(16).into()
+        };
+        let map_visibility: Element<Message> = if self.phase == GamePhase::NotStarted {
+            pick_list(
+                &MapVisibility::ALL[..],
+                Some(self.options.map),
+                Message::MapVisibilitySelected
+            ).into()
+        } else {
+            text(self.options.map.to_string()).size(16).into()
+        };
+        let guess_limit: Element<Message> = if self.phase == GamePhase::NotStarted {
+            pick_list(
+                &GuessLimit::ALL[..],
+                Some(self.options.guess_limit),
+                Message::GuessLimitSelected
+            ).into()
+        } else {
+            text(self.options.guess_limit.to_string()).size(16).into()
+        };
+        let game_mode = pick_list(
+            &GameMode::ALL[..],
+            Some(self.mode),
+            Message::GameModeSelected
+        );
+        let timer: Element<Message> = if self.phase == GamePhase::NotStarted {
+            let picker: Element<Message> = if self.mode == GameMode::EveryStation {
+                checkbox("Timer", self.timer() != TimerMode::Off).on_toggle(Message::TimerToggled).into()
+            } else {
+                pick_list(
+                    &TimerMode::ALL[..],
+                    Some(self.options.timer),
+                    Message::TimerModeSelected
+                ).into()
+            };
+            // The clock is already running before the first guess
+            if self.timer() == TimerMode::Off || !self.has_game() {
+                picker
+            } else {
+                let time = match self.time_left() {
+                    Some(left) => format!("Time left: {}", format_duration(left)),
+                    None => format!("Time: {}", format_duration(self.stopwatch.elapsed()))
+                };
+                row![picker, text(time).size(16)].spacing(5).align_items(iced::Alignment::Center).into()
+            }
+        } else if let Some(left) = self.time_left() {
+            text(format!("Time left: {}", format_duration(left))).size(16).into()
+        } else if self.timer() == TimerMode::Off && self.mode != GameMode::EveryStation {
+            text(self.options.timer.to_string()).size(16).into()
+        } else {
+            text(format!("Time: {}", format_duration(self.stopwatch.elapsed()))).size(16).into()
+        };
+        let mut hints = button("Hints");
+        if self.phase == GamePhase::Playing {
+            hints = hints.on_press(Message::ShowHints);
+        }
+        let mut setup = button("Setup");
+        if self.phase == GamePhase::NotStarted {
+            setup = setup.on_press(Message::ShowSetup);
+        }
         let clear_guesses = button("Restart")
             .on_press(Message::Restart);
-        let give_up = button("Give Up")
-            .on_press(Message::GiveUp);
-        let show_map = button(if self.viewing_map { "Hide Map" } else { "Show Map" })
-            .on_press(Message::ShowMap);
-        let play_along = button("Play Along")
-            .on_press(Message::PlayAlong);
+        let mut give_up = button("Give Up");
+        if !self.phase.is_over() && self.has_game() {
+            give_up = give_up.on_press(Message::GiveUp);
+        }
+        let mut show_map = button(if self.viewing_map { "Hide Map" } else { "Show Map" });
+        if !self.is_map_locked() {
+            show_map = show_map.on_press(Message::ShowMap);
+        }
+        let play_along: Element<Message> = if GameMode::SHARED.contains(&self.mode) {
+            button("Play Along").on_press(Message::PlayAlong).into()
+        } else {
+            // Say why there's no code to share
+            let reason = match self.mode {
+                GameMode::Daily => "Everyone already gets the same daily puzzle",
+                _ => "There's no target to share when guessing every station"
+            };
+            tooltip(button("Play Along"), text(reason).size(14), tooltip::Position::Bottom)
+                .style(theme::Container::Box)
+                .into()
+        };
 
         let overlaid = RenderOverlay::new(map_viewer, canvas(self));
 
         // === Layout ===
-        let input_row = row![
+        let counting_down = self.counting_down();
+        // Suggestions drop down over the map rather than pushing it down
+        let guess_input = DropDown::new(guess_input, self.suggestion_list(), !self.suggestions.is_empty());
+        let mut input_row = row![
             guess_input,
             guesses_text,
-            clear_guesses,
-            give_up,
-            show_map,
-            play_along
+            game_mode
         ].padding(5).spacing(5);
+        // Guessing every station has no target to measure closeness to, naming a
+        // station always measures along the map, and the daily target is picked
+        // from every station
+        match self.mode {
+            GameMode::FindTarget => {
+                input_row = input_row
+                    .push(distance_mode)
+                    .push(networks)
+                    .push(zone_hints)
+                    .push(direction_hints)
+                    .push(markers)
+                    .push(guess_limit)
+                    .push(setup);
+            }
+            GameMode::NameStation => {
+                input_row = input_row
+                    .push(zone_hints)
+                    .push(guess_limit)
+                    .push(setup);
+            }
+            GameMode::Daily => {
+                input_row = input_row
+                    .push(distance_mode)
+                    .push(zone_hints)
+                    .push(direction_hints)
+                    .push(markers)
+                    .push(guess_limit);
+            }
+            GameMode::EveryStation => {
+                input_row = input_row.push(networks);
+            }
+        }
+        if counting_down {
+            input_row = input_row.push(text(format!("Next puzzle in {}", format_duration(daily::until_tomorrow()))).size(16));
+        }
+        input_row = input_row.push(timer);
+        if self.mode != GameMode::EveryStation {
+            input_row = input_row.push(hints);
+        }
+        let input_row = input_row
+            .push(map_visibility)
+            .push(clear_guesses)
+            .push(give_up)
+            .push(show_map)
+            .push(play_along);
+
+        // Rows that come and go live in their own column so that the map
+        // stays at the same place in the widget tree and keeps its zoom
+        let mut status = Column::new();
+        if let Some(error) = &self.save_error {
+            status = status.push(
+                container(text(error).size(16).style(Color::from_rgb8(200, 0, 0))).padding([0, 5])
+            );
+        }
+        if let Some(last_guess) = self.last_guess_row() {
+            status = status.push(last_guess);
+        }
+        if !self.hints_used.is_empty() {
+            status = status.push(self.hint_row());
+        }
 
         let column_layout = Column::new()
             .push(input_row)
-            .push(overlaid);
+            .push(status);
+        // The map always sits in the same row, even before there's a panel
+        // beside it, so the first guess doesn't reset its zoom
+        let side_panel = if self.mode == GameMode::EveryStation {
+            self.progress_panel()
+        } else if self.guess_log.is_empty() {
+            Space::with_width(0).into()
+        } else {
+            self.guess_panel()
+        };
+        let column_layout = column_layout.push(row![overlaid, side_panel]);
 
         let main_container = container(
             column_layout,
@@ -253,10 +770,25 @@ impl Application for TubeTagApp {
             .center_y();
 
         // Construct modal
-        let modal_window = if self.show_modal {
+        let modal_window = if !self.candidates.is_empty() {
+            Some(self.candidate_card())
+        } else if self.confirming_give_up {
+            Some(self.give_up_card())
+        } else if self.confirming_restart {
+            Some(self.restart_card())
+        } else if self.show_summary {
+            self.summary_card()
+        } else if self.show_hints {
+            Some(self.hints_card())
+        } else if self.show_setup {
+            Some(self.setup_card())
+        } else if self.show_modal {
             Some(
                 Card::new(
-                    text(format!("Game code: {}", self.game_code)),
+                    text(match self.game_code {
+                        Some(code) => format!("Game code: {}", code),
+                        None => String::from("This game can't be shared")
+                    }),
                     text_input("Enter a code", &self.game_code_input)
                         .on_input(Message::GameCodeInputChanged)
                         .on_submit(Message::GameCodeSubmitted)
@@ -280,124 +812,1299 @@ impl Application for TubeTagApp {
 impl TubeTagApp {
     fn restart_game(&mut self) {
         self.guessed_stations.clear();
+        self.guess_log.clear();
+        self.last_guess = None;
+        self.hints_used.clear();
+        self.hint_region = None;
+        self.show_hints = false;
 
         // Reset num guesses
         self.num_guesses = 0;
-
-        // Pick random target station
-        let mut rng = rand::thread_rng();
-        let random_idx = rng.gen_range(0..self.all_stations.len());
-        self.target_station = Some(random_idx);
+        self.phase = GamePhase::NotStarted;
+        self.confirming_give_up = false;
+        self.confirming_restart = false;
+        self.show_summary = false;
+        self.daily_counts = false;
         self.title = None;
+        self.flash = None;
+
+        if self.mode == GameMode::EveryStation {
+            // Every station has to be found, so there's no target
+            self.target_station = None;
+            self.update_metric();
+            self.update_suggestions();
+            self.start_clock();
+            return
+        }
+
+        if self.mode == GameMode::Daily {
+            // Everyone playing today gets the same target from the default pool
+            let day = daily::today();
+            let pool = self.pool_under(self.mode, &GameOptions::default());
+            self.daily_day = Some(day);
+            self.set_target(pool[daily_index(day, self.dataset_version, pool.len())]);
+            self.update_game_code();
+            self.start_clock();
+            return
+        }
+
+        // Pick random target station from the pool, if the
+        // setup leaves nothing to pick there's no game to start
+        let pool = self.target_pool();
+        if pool.is_empty() {
+            self.target_station = None;
+            self.update_metric();
+        } else {
+            self.set_target(pool[rand::thread_rng().gen_range(0..pool.len())]);
+        }
+        self.start_clock();
 
         // Update game code
         self.update_game_code();
     }
 
-    fn update_game_code(&mut self) {
-        if self.target_station.is_none() {
-            return;
+    /// Times the game from zero. The clock runs from when there's a target to look
+    /// for rather than the first guess, so time spent studying the map counts.
+    /// The daily puzzle waits for the first guess instead, so that a countdown
+    /// can't use up the day's attempt before the player has started.
+    fn start_clock(&mut self) {
+        self.stopwatch = Stopwatch::default();
+        if self.has_game() && self.mode != GameMode::Daily {
+            self.stopwatch.start();
         }
+    }
 
-        let offset = (rand::random::<usize>() % 1000) * 1234;
-        self.game_code = (self.target_station.unwrap() + offset) ^ 0b0011010100101;
+    /// Every station that can be picked as the target under the current options.
+    fn target_pool(&self) -> Vec<usize> {
+        self.pool_under(self.mode, &self.options)
     }
 
-    fn search_approx(&self, query : &str) -> Vec<usize>{
-        // Search with search engine
-        let results: Vec<usize> = self.search_engine.search(query);
-        if results.is_empty() {
-            return results
+    fn pool_under(&self, mode: GameMode, options: &GameOptions) -> Vec<usize> {
+        // Naming the station and measuring along the map both need the target on the map
+        let needs_map = mode == GameMode::NameStation || options.distance_mode == DistanceMode::Map;
+        (0..self.all_stations.len())
+            .filter(|station_idx| options.pool.allows(&self.all_stations[*station_idx]))
+            .filter(|station_idx| self.on_networks(*station_idx, options.networks))
+            .filter(|station_idx| !needs_map || self.all_stations[*station_idx].map_position().is_some())
+            .collect()
+    }
+    /// Whether any line through the station is on one of the networks.
+    fn on_networks(&self, station_idx: usize, networks: NetworkFilter) -> bool {
+        self.all_stations[station_idx].lines.iter()
+            .any(|line_id| networks.allows(self.lines.get(*line_id).network))
+    }
+
+    /// How closeness is measured. Naming a station draws a line from each
+    /// guess to the target, so closeness is measured along the map to match.
+    fn distance_mode(&self) -> DistanceMode {
+        match self.mode {
+            GameMode::NameStation => DistanceMode::Map,
+            _ => self.options.distance_mode
         }
+    }
 
-        let first_idx = results[0];
-        let first_station = &self.all_stations[first_idx];
+    /// The markers this game is played with. Rings are drawn to scale on the map,
+    /// so they'd be misleading with any other distance mode, and pointless when
+    /// the target is already highlighted.
+    fn markers(&self) -> MarkerStyle {
+        match (self.mode, self.distance_mode()) {
+            (GameMode::NameStation, _) => MarkerStyle::Coloured,
+            (_, DistanceMode::Map) => self.options.markers,
+            _ => MarkerStyle::Coloured
+        }
+    }
 
-        if !first_station.name.contains("Edgware Road") {
-            return vec![first_idx]
+    /// Whether guesses point towards the target. There's nothing to
+    /// point at when naming a station, the target is highlighted.
+    fn direction_hints(&self) -> DirectionHints {
+        match self.mode {
+            GameMode::NameStation => DirectionHints::Off,
+            _ => self.options.direction_hints
         }
+    }
 
-        // Edgware Roads is a special case since we have 2 Edgware Roads
-        let mut duplicates: Vec<usize> = vec![];
-        for (idx, station) in self.all_stations.iter().enumerate() {
-            if station.name.contains("Edgware Road") {
-                duplicates.push(idx);
+    /// The timer this game is played with. Progress through every station
+    /// carries over between sessions, so that can only use a stopwatch.
+    fn timer(&self) -> TimerMode {
+        match (self.mode, self.options.timer) {
+            (GameMode::EveryStation, TimerMode::Countdown(_)) => TimerMode::Stopwatch,
+            (_, timer) => timer
+        }
+    }
 
-                if duplicates.len() == 2 {
-                    break
-                }
+    /// How long is left on the countdown, if there is one.
+    fn time_left(&self) -> Option<Duration> {
+        let limit = self.timer().limit()?;
+        Some(limit.saturating_sub(self.stopwatch.elapsed()))
+    }
+
+    /// Whether the countdown to the next daily puzzle is shown.
+    fn counting_down(&self) -> bool {
+        self.mode == GameMode::Daily && self.phase.is_over()
+    }
+
+    /// The first guess, or giving up straight away, is what uses up the day's attempt.
+    fn start_daily_attempt(&mut self) {
+        let Some(day) = self.daily_day else {
+            return
+        };
+        if self.mode != GameMode::Daily || self.phase != GamePhase::NotStarted {
+            return
+        }
+
+        self.stopwatch.start();
+        self.daily_counts = self.daily_stats.start(day);
+        let saved = self.daily_stats.save();
+        self.report_save("daily stats", saved);
+    }
+
+    /// Updates the daily stats with the result of a finished game, if it counts.
+    fn record_daily(&mut self) {
+        let Some(day) = self.daily_day else {
+            return
+        };
+        if self.mode != GameMode::Daily || !self.daily_counts {
+            return
+        }
+
+        self.daily_stats.finish(DailyResult {
+            day,
+            outcome: self.phase,
+            guesses: self.num_guesses,
+            hints: self.hints_used.clone(),
+            elapsed_secs: self.stopwatch.elapsed().as_secs()
+        });
+        let saved = self.daily_stats.save();
+        self.report_save("daily stats", saved);
+    }
+
+    /// Failed saves stay on screen until the next save works,
+    /// so the player knows their progress isn't being kept.
+    fn report_save(&mut self, what: &str, result: std::io::Result<()>) {
+        self.save_error = result.err().map(|error| format!("Couldn't save {}: {}", what, error));
+    }
+
+    /// Picks a new target if the current one is no longer allowed by the options.
+    fn target_options_changed(&mut self) {
+        if self.target_station.is_none_or(|target_idx| !self.target_pool().contains(&target_idx)) {
+            self.restart_game();
+        } else {
+            self.update_game_code();
+        }
+    }
+
+    /// Whether there's a game to play, every mode but finding
+    /// every station needs a target from the pool.
+    fn has_game(&self) -> bool {
+        self.mode == GameMode::EveryStation || self.target_station.is_some()
+    }
+
+    /// Whether the station can be guessed, the pool can restrict guesses as well as the target.
+    fn can_guess(&self, station_idx: usize) -> bool {
+        let pool = &self.options.pool;
+        !matches!(self.mode, GameMode::FindTarget | GameMode::NameStation) || !pool.guesses_too || self.groups.group(station_idx).members.iter()
+            .any(|member_idx| pool.allows(&self.all_stations[*member_idx]))
+    }
+
+    fn set_target(&mut self, target_idx: usize) {
+        self.target_station = Some(target_idx);
+        self.update_metric();
+        self.update_suggestions();
+    }
+
+    fn update_metric(&mut self) {
+        let Some(target_idx) = self.target_station else {
+            self.metric = None;
+            return
+        };
+
+        self.metric = Some(match self.distance_mode() {
+            DistanceMode::Map => Box::new(MapDistance::new(&self.all_stations[target_idx])),
+            DistanceMode::Stops => Box::new(StopDistance::new(&self.network, target_idx, 0)),
+            DistanceMode::StopsAndChanges => Box::new(StopDistance::new(&self.network, target_idx, CHANGE_COST)),
+            DistanceMode::Geographic => Box::new(GeoDistance::new(&self.all_stations, target_idx))
+        });
+    }
+
+    fn update_game_code(&mut self) {
+        let num_lines = self.lines.iter().count();
+        // Share the rules the game is actually played under, not settings the mode ignores
+        let options = GameOptions {
+            distance_mode: self.distance_mode(),
+            direction_hints: self.direction_hints(),
+            markers: self.markers(),
+            ..self.options
+        };
+        self.game_code = self.target_station.and_then(|target_idx| options.encode(self.mode, target_idx, num_lines));
+    }
+
+    /// The best matching station from each group the query could mean, best first.
+    fn search_candidates(&self, query: &str) -> Vec<usize> {
+        // An exact name or alias beats anything fuzzy
+        let mut results = self.search_engine.search_exact(query);
+        if results.is_empty() {
+            results = self.search_engine.search(query);
+        }
+
+        let mut candidates = self.distinct_groups(results);
+        candidates.truncate(MAX_CANDIDATES);
+        candidates
+    }
+
+    /// Keeps only the first station from each group.
+    fn distinct_groups(&self, station_indices: Vec<usize>) -> Vec<usize> {
+        let mut distinct: Vec<usize> = vec![];
+        for station_idx in station_indices {
+            if !distinct.iter().any(|distinct_idx| self.groups.same_group(*distinct_idx, station_idx)) {
+                distinct.push(station_idx);
             }
         }
-        return duplicates
+        distinct
     }
 
-    fn guess_submitted(&mut self) {
-        let station_indices = self.search_approx(&self.station_input);
+    fn update_suggestions(&mut self) {
+        // Suggesting names would give away the stations that are left
+        if self.mode == GameMode::EveryStation {
+            self.suggestions.clear();
+            self.selected_suggestion = None;
+            return
+        }
+
+        let results = self.search_engine.suggest(&self.station_input);
+        let mut suggestions = self.distinct_groups(results);
+
+        // The target is suggested like any other station, leaving it out would give it away
+        suggestions.retain(|station_idx| self.can_guess(*station_idx));
+        suggestions.truncate(MAX_SUGGESTIONS);
+        self.suggestions = suggestions;
+        self.selected_suggestion = None;
+    }
 
-        // Input was not a valid station
-        if station_indices.is_empty() {
+    fn guess_submitted(&mut self) -> Command<Message> {
+        if self.phase.is_over() {
+            return Command::none()
+        }
+        if self.mode == GameMode::EveryStation {
+            return self.every_station_submitted()
+        }
+        let candidates = self.search_candidates(&self.station_input);
+
+        match candidates.len() {
+            // Input was not a valid station
+            0 => {
+                self.title = Some(Title::new(
+                    "Unknown Station",
+                    Color::from_rgb8(200, 0,0),
+                    None,
+                    Duration::from_secs(2)
+                ));
+            }
+            1 => return self.guess_station(candidates[0]),
+            // Ask the player which one they meant before counting the guess
+            _ => {
+                self.candidates = candidates;
+                self.selected_candidate = 0;
+            }
+        }
+        Command::none()
+    }
+
+    /// Finding every station only accepts exact names and aliases, so misspellings
+    /// don't count and there is no picker listing the stations that are left.
+    fn every_station_submitted(&mut self) -> Command<Message> {
+        let matched = self.distinct_groups(self.search_engine.search_exact(&self.station_input));
+        let known = !matched.is_empty();
+        let found: Vec<usize> = matched.into_iter()
+            .filter(|station_idx| self.on_networks(*station_idx, self.options.networks))
+            .collect();
+        let Some(first_idx) = found.first().copied() else {
             self.title = Some(Title::new(
-                "Unknown Station",
+                if known { "Not on the Underground" } else { "Unknown Station" },
                 Color::from_rgb8(200, 0,0),
                 None,
                 Duration::from_secs(2)
             ));
-            return;
+            return Command::none()
+        };
+
+        // Stations sharing a name are all found at once
+        let unguessed: Vec<usize> = found.into_iter()
+            .filter(|station_idx| !self.guessed_stations.contains(station_idx))
+            .collect();
+        if unguessed.is_empty() {
+            return self.guess_station(first_idx)
+        }
+        let mut commands = vec![];
+        for station_idx in unguessed {
+            commands.push(self.guess_station(station_idx));
         }
+        Command::batch(commands)
+    }
+
+    fn candidate_confirmed(&mut self) -> Command<Message> {
+        let station_idx = self.candidates[self.selected_candidate];
+        self.candidates.clear();
+        self.guess_station(station_idx)
+    }
 
-        // Station is valid, update guess count
+    fn guess_station(&mut self, station_idx: usize) -> Command<Message> {
+        if self.phase.is_over() {
+            return Command::none()
+        }
+
+        // Guessing a station guesses every station in its group
+        let mut station_indices = self.groups.group(station_idx).members.clone();
+        // Keep the station that was guessed first
+        station_indices.sort_by_key(|idx| *idx != station_idx);
+
+        if !self.can_guess(station_idx) {
+            self.title = Some(Title::new(
+                "Not in the pool",
+                Color::from_rgb8(200, 0, 0),
+                None,
+                Duration::from_secs(2)
+            ));
+            return Command::none()
+        }
+
+        // Guessing a station that's already on the map doesn't cost anything
+        if station_indices.iter().any(|idx| self.guessed_stations.contains(idx)) {
+            self.title = Some(Title::new(
+                "Already guessed",
+                Color::from_rgb8(255, 165, 0),
+                None,
+                Duration::from_secs(2)
+            ));
+            self.flash = Some((station_idx, Instant::now()));
+            self.station_input = String::new();
+            self.update_suggestions();
+            return Command::none()
+        }
+
+        // Ticks only come once a second, so the time could have run out since the last one
+        if self.time_left() == Some(Duration::ZERO) {
+            return self.lose_game(GamePhase::TimedOut)
+        }
+
+        self.start_daily_attempt();
+
+        // Update guess count
         self.num_guesses += 1;
+        self.phase = GamePhase::Playing;
+        self.guess_log.push(station_idx);
 
-        // Add stations to 'guessed_stations' and check for win
-        for station_idx in station_indices {
-            self.guessed_stations.insert(station_idx);
+        self.last_guess = Some(station_indices[0]);
 
-            if let Some(target_idx) = self.target_station {
-                if station_idx == target_idx {
-                    self.game_won()
-                }
+        // Tell the player how far away they are, if we know
+        if let Some(title) = self.distance_title(station_indices[0]) {
+            self.title = Some(title);
+        }
+
+        // Add stations to 'guessed_stations' and check for win,
+        // these are all in the same group so we only need to check one
+        let guessed_idx = station_indices[0];
+        self.guessed_stations.extend(station_indices);
+        if let Some(target_idx) = self.target_station {
+            if self.groups.same_group(guessed_idx, target_idx) {
+                self.game_won()
+            }
+        }
+        if self.mode == GameMode::EveryStation {
+            if self.guessed_stations.len() == self.every_station_pool().len() {
+                self.game_won()
+            } else {
+                self.title = Some(Title::new(
+                    &format!("Found {}", self.groups.group(guessed_idx).name),
+                    Color::from_rgb8(0, 200, 0),
+                    None,
+                    Duration::from_secs(2)
+                ));
+                self.save_progress();
             }
         }
         self.station_input = String::new();
+        self.update_suggestions();
+
+        // The last guess has been used without finding the target
+        if !self.phase.is_over() && self.guesses_left() == Some(0) {
+            return self.lose_game(GamePhase::OutOfGuesses)
+        }
+        Command::none()
+    }
+
+    /// The most guesses allowed this game, if there's a limit.
+    fn guess_limit(&self) -> Option<usize> {
+        // Guessing every station is never cut short
+        match self.mode {
+            GameMode::EveryStation => None,
+            GameMode::FindTarget | GameMode::NameStation | GameMode::Daily => self.options.guess_limit.max()
+        }
+    }
+
+    fn guesses_left(&self) -> Option<usize> {
+        self.guess_limit().map(|limit| limit.saturating_sub(self.num_guesses))
+    }
+
+    /// Every line serving the station's group, in the order they are first listed.
+    fn group_lines(&self, station_idx: usize) -> Vec<LineId> {
+        let mut lines = vec![];
+        for member_idx in &self.groups.group(station_idx).members {
+            for line_id in &self.all_stations[*member_idx].lines {
+                if !lines.contains(line_id) {
+                    lines.push(*line_id);
+                }
+            }
+        }
+        lines
+    }
+
+    /// Card asking which of several matching stations the player meant.
+    fn candidate_card(&self) -> Card<'_, Message, Theme, Renderer> {
+        let mut choices = Column::new().spacing(5);
+        for (idx, station_idx) in self.candidates.iter().enumerate() {
+            let mut label = Row::new()
+                .push(text(format!("{}. {}:", idx + 1, self.groups.group(*station_idx).name)).size(16))
+                .spacing(5);
+            for line_id in self.group_lines(*station_idx) {
+                let line = self.lines.get(line_id);
+                label = label.push(text(&line.name).size(16).style(line.colour));
+            }
+
+            let style = if idx == self.selected_candidate {
+                theme::Button::Primary
+            } else {
+                theme::Button::Secondary
+            };
+            choices = choices.push(
+                button(label)
+                    .width(Length::Fill)
+                    .style(style)
+                    .on_press(Message::CandidatePicked(idx))
+            );
+        }
+
+        Card::new(text("Which station did you mean?"), choices)
+            .foot(text("Use the arrow keys or numbers to choose, Enter to guess").size(14))
+            .max_width(500.0)
+            .on_close(Message::CloseModal)
+    }
+
+    /// List of suggestions shown under the guess input.
+    fn suggestion_list(&self) -> Element<'_, Message> {
+        let mut suggestions = Column::new()
+            .padding([0, 5])
+            .spacing(2);
+        for (idx, station_idx) in self.suggestions.iter().enumerate() {
+            let mut label = Row::new()
+                .push(text(&self.groups.group(*station_idx).name).size(16))
+                .spacing(5);
+            for line_id in self.group_lines(*station_idx) {
+                let line = self.lines.get(line_id);
+                label = label.push(text(&line.name).size(14).style(line.colour));
+            }
+            if self.guessed_stations.contains(station_idx) {
+                label = label.push(text("(guessed)").size(14).style(Color::from_rgb8(150, 150, 150)));
+            }
+
+            let style = if self.selected_suggestion == Some(idx) {
+                theme::Button::Primary
+            } else {
+                theme::Button::Text
+            };
+            suggestions = suggestions.push(
+                button(label)
+                    .style(style)
+                    .on_press(Message::SuggestionPicked(idx))
+            );
+        }
+        container(suggestions)
+            .style(theme::Container::Box)
+            .into()
+    }
+
+    /// Row showing the last guessed station, its lines, and any zone hint.
+    fn last_guess_row(&self) -> Option<Element<'_, Message>> {
+        let last_idx = self.last_guess?;
+        let last_station = &self.all_stations[last_idx];
+
+        let mut last_guess = Row::new()
+            .push(text(format!("{}:", last_station.name)).size(16))
+            .padding(5)
+            .spacing(5);
+        for line_id in &last_station.lines {
+            let line = self.lines.get(*line_id);
+            last_guess = last_guess.push(text(&line.name).size(16).style(line.colour));
+        }
+        if let Some(zone_hint) = self.zone_hint(last_station) {
+            last_guess = last_guess.push(zone_hint);
+        }
+        Some(last_guess.into())
+    }
+
+    /// Whether a hint can be revealed, it can't use up the last guess.
+    fn can_reveal(&self, hint: Hint) -> bool {
+        !self.is_revealed(hint) && self.is_useful(hint) && self.guesses_left().is_none_or(|left| left > hint.cost())
+    }
+
+    /// Whether the hint is already known, free zone hints don't need paying for.
+    fn is_revealed(&self, hint: Hint) -> bool {
+        self.hints_used.contains(&hint) || (hint == Hint::Zone && self.options.zone_hints == ZoneHints::Free)
+    }
+
+    /// Whether the hint tells the player anything they can't already see.
+    fn is_useful(&self, hint: Hint) -> bool {
+        match hint {
+            // The target is already highlighted when naming it,
+            // and there's nowhere to shade for targets off the map
+            Hint::Region => self.mode != GameMode::NameStation
+                && self.target_station.is_some_and(|target_idx| self.all_stations[target_idx].map_position().is_some()),
+            // So are the lines running through it, unless the map is hidden
+            Hint::Line => self.mode != GameMode::NameStation || self.is_blind(),
+            _ => true
+        }
+    }
+
+    /// Whether each guess's zone is compared with the target's,
+    /// this follows from knowing which zone the target is in.
+    fn zone_hint_revealed(&self) -> bool {
+        self.is_revealed(Hint::Zone)
+    }
+
+    fn reveal_hint(&mut self, hint: Hint) {
+        let Some(target_idx) = self.target_station else {
+            return
+        };
+        self.num_guesses += hint.cost();
+        self.hints_used.push(hint);
+
+        if hint == Hint::Region {
+            // Move the region off centre so the target isn't just in the middle of it
+            let mut rng = rand::thread_rng();
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let distance = rng.gen_range(0.0..HINT_REGION_RADIUS * 0.7);
+            let Some(target) = self.all_stations[target_idx].map_position() else {
+                return
+            };
+            self.hint_region = Some((target.0 + angle.cos() * distance, target.1 + angle.sin() * distance));
+        }
+    }
+
+    /// What a revealed hint says about the target.
+    fn hint_text(&self, hint: Hint) -> String {
+        let Some(target_idx) = self.target_station else {
+            return String::new()
+        };
+        let target = &self.all_stations[target_idx];
+        let name = &self.groups.group(target_idx).name;
+
+        match hint {
+            Hint::WordCount => match name.split_whitespace().count() {
+                1 => String::from("1 word"),
+                words => format!("{} words", words)
+            },
+            Hint::Zone if target.zones.is_empty() => String::from("Outside the zones"),
+            Hint::Zone => target.zones_name(),
+            Hint::Line => match self.group_lines(target_idx).first() {
+                Some(line_id) => format!("Served by {}", self.lines.get(*line_id).name),
+                None => String::from("Not on any line")
+            },
+            Hint::Region => String::from("Somewhere in the shaded area"),
+            Hint::FirstLetter => format!("Starts with {}", name.chars().next().unwrap_or(' '))
+        }
+    }
+
+    /// Row listing what the hints used so far have revealed.
+    fn hint_row(&self) -> Element<'_, Message> {
+        let mut hints = Row::new()
+            .push(text("Hints:").size(16))
+            .padding(5)
+            .spacing(10);
+        for hint in &self.hints_used {
+            hints = hints.push(text(self.hint_text(*hint)).size(16));
+        }
+        hints.into()
+    }
+
+    /// Card offering the hints, each costing some guesses.
+    fn hints_card(&self) -> Card<'_, Message, Theme, Renderer> {
+        let mut body = Column::new().spacing(5);
+        for hint in Hint::ALL.into_iter().filter(|hint| self.is_useful(*hint)) {
+            let reveal: Element<Message> = if self.is_revealed(hint) {
+                text(self.hint_text(hint)).size(16).into()
+            } else {
+                let cost = match hint.cost() {
+                    1 => String::from("+1 Guess"),
+                    cost => format!("+{} Guesses", cost)
+                };
+                let mut reveal = button(text(format!("Reveal ({})", cost)).size(16));
+                if self.can_reveal(hint) {
+                    reveal = reveal.on_press(Message::HintRevealed(hint));
+                }
+                reveal.into()
+            };
+            body = body.push(row![
+                text(hint.to_string()).size(16).width(150),
+                reveal
+            ].spacing(10).align_items(iced::Alignment::Center));
+        }
+
+        Card::new(text("Hints"), body)
+            .foot(button("Keep Guessing").style(theme::Button::Secondary).on_press(Message::CloseModal))
+            .max_width(500.0)
+            .on_close(Message::CloseModal)
+    }
+
+    fn zone_hint(&self, last_station: &Station) -> Option<Element<'_, Message>> {
+        let target_idx = self.target_station?;
+
+        match self.options.zone_hints {
+            ZoneHints::Off => None,
+            _ if self.zone_hint_revealed() => {
+                let comparison = last_station.compare_zones(&self.all_stations[target_idx])?;
+                let hint = format!("- {}, {}", last_station.zones_name(), comparison);
+                Some(text(hint).size(16).into())
+            }
+            ZoneHints::Free => None,
+            // Nothing left to spend guesses on
+            ZoneHints::Paid if self.phase.is_over() => None,
+            ZoneHints::Paid => {
+                let mut reveal = button("Zone Hint (+1 Guess)");
+                if self.can_reveal(Hint::Zone) {
+                    reveal = reveal.on_press(Message::HintRevealed(Hint::Zone));
+                }
+                Some(reveal.into())
+            }
+        }
+    }
+
+    /// Side panel listing every guess made so far.
+    fn guess_panel(&self) -> Element<'_, Message> {
+        let mut entries = Column::new().spacing(2);
+        for (number, station_idx) in self.guess_log.ordered(self.guess_order, |idx| self.station_closeness(idx)) {
+            let station = &self.all_stations[station_idx];
+
+            // Rings leave the player to judge closeness from the map
+            let coloured = self.markers() == MarkerStyle::Coloured;
+            let mut heading = Row::new()
+                .spacing(5)
+                .align_items(iced::Alignment::Center);
+            if coloured {
+                heading = heading.push(container(Space::new(12, 12))
+                    .style(theme::Container::Custom(Box::new(Swatch(closeness_colour(self.station_closeness(station_idx)))))));
+            }
+            heading = heading.push(text(format!("{}. {}", number, station.name)).size(16));
+            if let Some(km) = self.distance_km(station_idx) {
+                heading = heading.push(text(format!("{:.1} km", km)).size(14));
+            }
+
+            let mut lines = Row::new().spacing(5);
+            for line_id in &station.lines {
+                let line = self.lines.get(*line_id);
+                lines = lines.push(text(&line.name).size(12).style(line.colour));
+            }
+            let mut entry = column![heading, lines].spacing(2);
+            if coloured {
+                entry = entry.push(text(format!("Closer than {:.0}% of stations", self.closeness_rank(station_idx) * 100.0)).size(12));
+            }
+
+            entries = entries.push(
+                button(entry)
+                    .width(Length::Fill)
+                    .style(theme::Button::Text)
+                    .on_press(Message::GuessLogSelected(station_idx))
+            );
+        }
+
+        column![
+            text("Guesses").size(20),
+            pick_list(&GuessOrder::ALL[..], Some(self.guess_order), Message::GuessOrderSelected),
+            scrollable(entries)
+        ]
+            .width(300)
+            .padding(5)
+            .spacing(5)
+            .into()
+    }
+
+    /// How close the station is to the target, from 0 (far) to 1 (the target).
+    fn station_closeness(&self, station_idx: usize) -> f32 {
+        self.metric.as_ref()
+            .map_or(0.0, |metric| metric.closeness(station_idx, self.all_stations[station_idx].map_position().as_ref()))
+    }
+
+    /// Fraction of the other stations that are further from the target than this one.
+    fn closeness_rank(&self, station_idx: usize) -> f32 {
+        let closeness = self.station_closeness(station_idx);
+        let further = (0..self.all_stations.len())
+            .filter(|other_idx| *other_idx != station_idx && self.station_closeness(*other_idx) < closeness)
+            .count();
+        further as f32 / (self.all_stations.len() - 1).max(1) as f32
+    }
+
+    fn distance_km(&self, station_idx: usize) -> Option<f64> {
+        let target_idx = self.target_station?;
+        let station_position = self.all_stations[station_idx].geo_position()?;
+        let target_position = self.all_stations[target_idx].geo_position()?;
+        Some(haversine_km(station_position, target_position))
+    }
+
+    fn distance_title(&self, station_idx: usize) -> Option<Title> {
+        let km = self.distance_km(station_idx)?;
+        let colour = match self.markers() {
+            MarkerStyle::Coloured => closeness_colour(self.station_closeness(station_idx)),
+            MarkerStyle::Rings => Color::WHITE
+        };
+        Some(Title::new(
+            &format!("{:.1} km away", km),
+            colour,
+            None,
+            Duration::from_secs(2)
+        ))
     }
 
     fn game_won(&mut self) {
+        self.phase = GamePhase::Won;
+        self.stopwatch.stop();
+        match self.mode {
+            // Show the summary if there's more to it than the number of guesses
+            GameMode::FindTarget | GameMode::NameStation => {
+                self.show_summary = self.timer() != TimerMode::Off || !self.hints_used.is_empty()
+            }
+            GameMode::Daily => {
+                self.record_daily();
+                self.show_summary = true;
+            }
+            GameMode::EveryStation => {
+                self.report_save("progress", CompletionSave::delete());
+                self.show_summary = true;
+            }
+        }
         self.title = Some(Title::new(
             "You Won!",
             Color::from_rgb8(0, 255,0),
             Some(Color::from_rgba8(255, 255, 255, 0.5)),
             Duration::from_secs(15)
         ));
+    }
 
-        for idx in 0..self.all_stations.len() {
-            self.guessed_stations.insert(idx);
+    /// Ends the game without the target being found and zooms in on where it was.
+    fn lose_game(&mut self, phase: GamePhase) -> Command<Message> {
+        self.start_daily_attempt();
+        self.phase = phase;
+        self.stopwatch.stop();
+        self.candidates.clear();
+        self.title = None;
+        self.show_hints = false;
+        self.show_summary = true;
+
+        match self.mode {
+            GameMode::FindTarget | GameMode::NameStation => { }
+            GameMode::Daily => self.record_daily(),
+            GameMode::EveryStation => self.report_save("progress", CompletionSave::delete())
         }
+        let Some(target_idx) = self.target_station else {
+            return Command::none()
+        };
+
+        match self.all_stations[target_idx].map_position() {
+            Some(position) => Command::widget(CentreMap { position, scale: Some(GIVE_UP_SCALE) }),
+            None => Command::none()
+        }
+    }
+
+    /// The guess that came closest to the target.
+    fn closest_guess(&self) -> Option<usize> {
+        let ordered = self.guess_log.ordered(GuessOrder::Closeness, |idx| self.station_closeness(idx));
+        ordered.first().map(|(_, station_idx)| *station_idx)
+    }
+
+    fn give_up_card(&self) -> Card<'_, Message, Theme, Renderer> {
+        let buttons = row![
+            button("Give Up").on_press(Message::GiveUpConfirmed),
+            button("Keep Guessing").style(theme::Button::Secondary).on_press(Message::CloseModal)
+        ].spacing(10);
+
+        let consequence = match self.mode {
+            GameMode::FindTarget | GameMode::NameStation | GameMode::Daily => "The answer will be shown on the map.",
+            GameMode::EveryStation => "The stations you missed will be listed."
+        };
+        Card::new(text("Give up?"), text(consequence))
+            .foot(buttons)
+            .max_width(500.0)
+            .on_close(Message::CloseModal)
+    }
+
+    fn restart_card(&self) -> Card<'_, Message, Theme, Renderer> {
+        let buttons = row![
+            button("Restart").on_press(Message::RestartConfirmed),
+            button("Keep Guessing").style(theme::Button::Secondary).on_press(Message::CloseModal)
+        ].spacing(10);
+
+        let found = format!("The {} stations you've found so far won't be kept.", self.guessed_stations.len());
+        Card::new(text("Restart?"), text(found))
+            .foot(buttons)
+            .max_width(500.0)
+            .on_close(Message::CloseModal)
+    }
+
+    /// Card explaining the answer once the game is over.
+    fn summary_card(&self) -> Option<Card<'_, Message, Theme, Renderer>> {
+        if self.mode == GameMode::EveryStation {
+            return Some(self.every_station_summary())
+        }
+        let target_idx = self.target_station?;
+
+        let mut lines = Row::new().spacing(5);
+        for line_id in self.group_lines(target_idx) {
+            let line = self.lines.get(line_id);
+            lines = lines.push(text(&line.name).size(16).style(line.colour));
+        }
+
+        let result = match self.phase {
+            GamePhase::Won => format!("Found in {} guesses.", self.num_guesses),
+            GamePhase::OutOfGuesses => format!("Ran out of guesses after {} guesses.", self.num_guesses),
+            GamePhase::TimedOut => format!("Ran out of time after {} guesses.", self.num_guesses),
+            _ => format!("Gave up after {} guesses.", self.num_guesses)
+        };
+        let closest = match self.closest_guess() {
+            Some(closest_idx) => {
+                let mut closest = format!("Your closest guess was {}", self.all_stations[closest_idx].name);
+                let mut away = vec![];
+                match self.network.hops(closest_idx, target_idx) {
+                    Some(1) => away.push(String::from("1 stop")),
+                    Some(stops) => away.push(format!("{} stops", stops)),
+                    None => ()
+                }
+                if let Some(km) = self.distance_km(closest_idx) {
+                    away.push(format!("{:.1} km", km));
+                }
+                if !away.is_empty() {
+                    closest += &format!(", {} away", away.join(" and "));
+                }
+                format!(
+                    "{}, closer than {:.0}% of stations.",
+                    closest,
+                    self.closeness_rank(closest_idx) * 100.0
+                )
+            }
+            None => String::from("You didn't make any guesses.")
+        };
+        let mut body = column![
+            lines,
+            text(result).size(16)
+        ].spacing(5);
+        if self.phase != GamePhase::Won {
+            body = body.push(text(closest).size(16));
+        }
+        if self.timer() != TimerMode::Off {
+            body = body.push(text(format!("Time: {}", format_duration(self.stopwatch.elapsed()))).size(16));
+        }
+        if !self.hints_used.is_empty() {
+            let used: Vec<String> = self.hints_used.iter().map(Hint::to_string).collect();
+            body = body.push(text(format!("Hints used: {}", used.join(", "))).size(16));
+        }
+        if self.mode == GameMode::Daily {
+            let stats = &self.daily_stats;
+            if !self.daily_counts {
+                body = body.push(text("Only your first attempt at today's puzzle counts.").size(16));
+                if let Some(counted) = stats.last_result.as_ref().filter(|result| Some(result.day) == self.daily_day) {
+                    let mut attempt = match counted.outcome {
+                        GamePhase::Won => format!("That attempt found it in {} guesses", counted.guesses),
+                        GamePhase::GaveUp => format!("That attempt gave up after {} guesses", counted.guesses),
+                        GamePhase::OutOfGuesses => format!("That attempt ran out of guesses after {}", counted.guesses),
+                        GamePhase::TimedOut => format!("That attempt ran out of time after {} guesses", counted.guesses),
+                        _ => format!("That attempt didn't find it after {} guesses", counted.guesses)
+                    };
+                    attempt += &format!(", taking {}", format_duration(Duration::from_secs(counted.elapsed_secs)));
+                    if !counted.hints.is_empty() {
+                        let used: Vec<String> = counted.hints.iter().map(Hint::to_string).collect();
+                        attempt += &format!(", using {}", used.join(", "));
+                    }
+                    body = body.push(text(format!("{}.", attempt)).size(16));
+                }
+            }
+            body = body
+                .push(text(format!(
+                    "Played {}, won {}, current streak {}, best streak {}, hints used {}",
+                    stats.played, stats.won, stats.current_streak, stats.best_streak, stats.hints_used
+                )).size(16))
+                .push(text(format!("Next puzzle in {}", format_duration(daily::until_tomorrow()))).size(16));
+        }
+
+        let buttons = row![
+            button("Play Again").on_press(Message::Restart),
+            button("View Map").style(theme::Button::Secondary).on_press(Message::CloseModal)
+        ].spacing(10);
+
+        Some(
+            Card::new(text(format!("The answer was {}", self.groups.group(target_idx).name)), body)
+                .foot(buttons)
+                .max_width(500.0)
+                .on_close(Message::CloseModal)
+        )
+    }
+
+    /// Card for restricting the target pool before the game starts.
+    fn setup_card(&self) -> Card<'_, Message, Theme, Renderer> {
+        let pool = self.options.pool;
+
+        // Lines are grouped by network and laid out three to a row
+        let mut lines = Column::new().spacing(5);
+        for network in RailNetwork::ALL {
+            let network_lines: Vec<_> = self.lines.iter()
+                .take(TargetPool::MAX_LINES)
+                .filter(|(_, line)| line.network == network)
+                .collect();
+            if network_lines.is_empty() {
+                continue
+            }
+
+            lines = lines.push(text(network.to_string()).size(16));
+            let mut line_row = Row::new().spacing(10);
+            for (idx, (line_id, line)) in network_lines.into_iter().enumerate() {
+                if idx > 0 && idx % 3 == 0 {
+                    lines = lines.push(line_row);
+                    line_row = Row::new().spacing(10);
+                }
+                line_row = line_row.push(
+                    row![
+                        checkbox("", pool.has_line(line_id)).on_toggle(move |picked| Message::PoolLineToggled(line_id, picked)),
+                        text(&line.name).size(16).style(line.colour)
+                    ].width(150)
+                );
+            }
+            lines = lines.push(line_row);
+        }
+
+        let mut zones = Row::new().spacing(10);
+        for zone in 1..=TargetPool::MAX_ZONE {
+            zones = zones.push(
+                checkbox(zone.to_string(), pool.has_zone(zone)).on_toggle(move |picked| Message::PoolZoneToggled(zone, picked))
+            );
+        }
+
+        let pool_size = self.target_pool().len();
+        let summary = if pool_size == 0 {
+            String::from("No stations match, pick more lines or zones to start a game.")
+        } else {
+            format!("{} stations could be the target.", pool_size)
+        };
+
+        let body = column![
+            text("Lines").size(18),
+            lines,
+            text("Zones").size(18),
+            zones,
+            checkbox("Only allow guesses from the pool", pool.guesses_too).on_toggle(Message::PoolGuessesToggled),
+            text(summary).size(16)
+        ].spacing(10);
+
+        Card::new(text("Game Setup"), body)
+            .foot(button("Done").on_press(Message::CloseModal))
+            .max_width(600.0)
+            .on_close(Message::CloseModal)
+    }
+
+    /// Whether the map is hidden, it's shown again once the game is over.
+    fn is_blind(&self) -> bool {
+        self.options.map == MapVisibility::Blind && !self.phase.is_over()
+    }
+
+    /// Whether showing every station on the map would give the game away.
+    fn is_map_locked(&self) -> bool {
+        let shows_answer = matches!(self.mode, GameMode::EveryStation | GameMode::NameStation);
+        self.is_blind() || (shows_answer && !self.phase.is_over())
+    }
+
+    /// Whether the station is the one being named, so its label can't be shown yet.
+    fn is_label_hidden(&self, station_idx: usize) -> bool {
+        self.mode == GameMode::NameStation && !self.phase.is_over() && self.target_station
+            .is_some_and(|target_idx| self.groups.same_group(station_idx, target_idx))
+    }
+
+    /// Every station served by the line.
+    /// Every station that has to be found, which is every station on the chosen networks.
+    fn every_station_pool(&self) -> Vec<usize> {
+        (0..self.all_stations.len())
+            .filter(|station_idx| self.on_networks(*station_idx, self.options.networks))
+            .collect()
+    }
+    fn line_stations(&self, line_id: LineId) -> Vec<usize> {
+        (0..self.all_stations.len())
+            .filter(|station_idx| self.all_stations[*station_idx].lines.contains(&line_id))
+            .collect()
+    }
+
+    fn save_progress(&mut self) {
+        if self.mode != GameMode::EveryStation || self.phase.is_over() {
+            return
+        }
+
+        let mut found: Vec<String> = self.guessed_stations.iter()
+            .map(|station_idx| self.all_stations[*station_idx].name.clone())
+            .collect();
+        found.sort();
+        let save = CompletionSave {
+            found,
+            elapsed_secs: self.stopwatch.elapsed().as_secs(),
+            networks: self.options.networks
+        };
+        self.report_save("progress", save.save());
+    }
+
+    /// Carries on a game of guessing every station from where it was saved.
+    fn resume_progress(&mut self) {
+        let Some(save) = CompletionSave::load() else {
+            return
+        };
+        self.options.networks = save.networks;
+
+        for name in &save.found {
+            if let Some(station_idx) = self.all_stations.iter().position(|station| &station.name == name) {
+                self.guessed_stations.insert(station_idx);
+            }
+        }
+        // The time is saved either way, so the player's timer choice is kept
+        self.stopwatch = Stopwatch::from_elapsed(Duration::from_secs(save.elapsed_secs));
+        self.stopwatch.start();
+        if !self.guessed_stations.is_empty() {
+            self.phase = GamePhase::Playing;
+        }
+    }
+
+    /// Side panel showing how many stations have been found, overall and on each line.
+    fn progress_panel(&self) -> Element<'_, Message> {
+        let total = self.every_station_pool().len();
+        let found = self.guessed_stations.len();
+
+        let mut lines = Column::new().spacing(5);
+        for (line_id, line) in self.lines.iter().filter(|(_, line)| self.options.networks.allows(line.network)) {
+            let stations = self.line_stations(line_id);
+            if stations.is_empty() {
+                continue
+            }
+            let line_found = stations.iter().filter(|station_idx| self.guessed_stations.contains(station_idx)).count();
+            lines = lines.push(column![
+                row![
+                    text(&line.name).size(14).style(line.colour),
+                    Space::with_width(Length::Fill),
+                    text(format!("{} / {}", line_found, stations.len())).size(14)
+                ],
+                progress_bar(0.0..=stations.len() as f32, line_found as f32).height(6)
+            ].spacing(2));
+        }
+
+        column![
+            text("Progress").size(20),
+            text(format!("{} / {} stations", found, total)).size(16),
+            progress_bar(0.0..=total as f32, found as f32).height(10),
+            scrollable(lines)
+        ]
+            .width(300)
+            .padding(5)
+            .spacing(5)
+            .into()
+    }
+
+    /// Card listing the stations that weren't found, by line.
+    fn every_station_summary(&self) -> Card<'_, Message, Theme, Renderer> {
+        let total = self.every_station_pool().len();
+        let found = self.guessed_stations.len();
+        let title = if found == total {
+            String::from("You found every station!")
+        } else {
+            format!("You found {} of {} stations", found, total)
+        };
+
+        let mut body = Column::new().spacing(5);
+        if self.timer() != TimerMode::Off {
+            body = body.push(text(format!("Time: {}", format_duration(self.stopwatch.elapsed()))).size(16));
+        }
+        for (line_id, line) in self.lines.iter().filter(|(_, line)| self.options.networks.allows(line.network)) {
+            let missed: Vec<&str> = self.line_stations(line_id).into_iter()
+                .filter(|station_idx| !self.guessed_stations.contains(station_idx))
+                .map(|station_idx| self.all_stations[station_idx].name.as_str())
+                .collect();
+            if missed.is_empty() {
+                continue
+            }
+            body = body
+                .push(text(format!("{} ({} missed)", line.name, missed.len())).size(16).style(line.colour))
+                .push(text(missed.join(", ")).size(14));
+        }
+
+        let buttons = row![
+            button("Play Again").on_press(Message::Restart),
+            button("View Map").style(theme::Button::Secondary).on_press(Message::CloseModal)
+        ].spacing(10);
+
+        Card::new(text(title), scrollable(body))
+            .foot(buttons)
+            .max_width(600.0)
+            .max_height(600.0)
+            .on_close(Message::CloseModal)
+    }
+
+    /// Which way the target is from a guessed station's marker, if direction hints are on.
+    /// Real geography is used when that's how closeness is measured.
+    fn direction_to_target(&self, station_idx: usize, point: Point, to_point: impl Fn(&(f32, f32)) -> Point) -> Option<Vector> {
+        let target_idx = self.target_station?;
+        if self.direction_hints() == DirectionHints::Off || self.groups.same_group(station_idx, target_idx) {
+            return None
+        }
+        // Once the game is over every station can be on the map, only guesses need pointing
+        if self.phase != GamePhase::Playing || !self.guessed_stations.contains(&station_idx) {
+            return None
+        }
+
+        let target = &self.all_stations[target_idx];
+        let geo = (self.all_stations[station_idx].geo_position(), target.geo_position());
+        let direction = match geo {
+            (Some(from), Some(to)) if self.distance_mode() == DistanceMode::Geographic => {
+                let (east, south) = geo_direction(from, to);
+                Vector::new(east as f32, south as f32)
+            }
+            _ => to_point(&target.map_position()?) - point
+        };
+
+        let length = (direction.x * direction.x + direction.y * direction.y).sqrt();
+        if length == 0.0 {
+            return None
+        }
+        Some(direction * (1.0 / length))
+    }
+
+    /// Stations to draw on the map.
+    fn visible_stations(&self) -> Vec<usize> {
+        // Winning shows every station, losing only shows where the target was
+        if self.viewing_map || self.phase == GamePhase::Won {
+            return (0..self.all_stations.len()).collect()
+        }
+
+        let mut stations: Vec<usize> = self.guessed_stations.iter().copied().collect();
+        let shows_target = self.phase.is_lost() || self.mode == GameMode::NameStation;
+        if let Some(target_idx) = self.target_station.filter(|_| shows_target) {
+            for member_idx in &self.groups.group(target_idx).members {
+                if !self.guessed_stations.contains(member_idx) {
+                    stations.push(*member_idx);
+                }
+            }
+        }
+        stations
     }
 }
 
 
-#[derive(Debug, Clone, Copy)]
-pub struct PubState {
-    pub scale: f32,
-    pub starting_offset: Vector,
-    pub current_offset: Vector,
-    pub cursor_grabbed_at: Option<Point>,
+/// Operation that pans the map so that a position on it is in the centre.
+struct CentreMap {
+    position: (f32, f32),
+    // Zoom to this scale too, if given
+    scale: Option<f32>
 }
 
+impl<T> Operation<T> for CentreMap {
+    fn container(
+        &mut self,
+        _id: Option<&Id>,
+        _bounds: Rectangle,
+        operate_on_children: &mut dyn FnMut(&mut dyn Operation<T>)
+    ) {
+        operate_on_children(self)
+    }
+
+    fn custom(&mut self, state: &mut dyn Any, _id: Option<&Id>) {
+        let Some(overlaid) = state.downcast_mut::<OverlaidState>() else {
+            return
+        };
+        if overlaid.tree.tag != Tag::of::<ViewerState>() {
+            return
+        }
+
+        let viewer_state = overlaid.tree.state.downcast_mut::<ViewerState>();
+        if let Some(scale) = self.scale {
+            viewer_state.scale = scale;
+        }
+
+        let coords = CoordinateSystem::new(overlaid.bounds.width, overlaid.bounds.height, viewer_state.scale);
+        viewer_state.pan_to(Vector::new(
+            coords.x_dist_percent(self.position.0 * CoordinateSystem::REL_X - 0.5),
+            coords.y_dist_percent(self.position.1 * CoordinateSystem::REL_Y - 0.5)
+        ));
+    }
+}
+
+/// Round patch of a single colour, matching the markers on the map.
+struct Swatch(Color);
+
+impl container::StyleSheet for Swatch {
+    type Style = Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            background: Some(self.0.into()),
+            border: Border {
+                color: Color::BLACK,
+                width: 1.0,
+                radius: 6.0.into()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+const GUESS_INPUT_ID: &str = "guess_input";
+
+// How far the map zooms in on the target after losing
+const GIVE_UP_SCALE: f32 = 3.0;
+
+// Radius of the map region hint, in pixels of the full size map
+const HINT_REGION_RADIUS: f32 = 600.0;
+
+// The ring around the station being named, it's bigger and
+// bolder than the other rings so it stands out from the map
+const NAMING_RING_RADIUS: f32 = 96.0;
+const NAMING_RING_WIDTH: f32 = 16.0;
+const NAMING_RING_COLOUR: Color = Color { r: 1.0, g: 0.0, b: 1.0, a: 1.0 };
+
+// How long the markers of a repeated guess are highlighted for
+const FLASH_DURATION: Duration = Duration::from_secs(2);
+
+// Most stations suggested while typing
+const MAX_SUGGESTIONS: usize = 6;
+
+// Most stations offered when a guess is ambiguous
+const MAX_CANDIDATES: usize = 5;
+
+// How many stops a change of line is worth
+const CHANGE_COST: usize = 1;
+
 const UNDERGROUND_FONT: Font = Font {
     family: Family::Name("P22 Underground Pro"),
     weight: Weight::Bold,
     ..Font::DEFAULT
 };
 
-fn average_position(positions: &Vec<(f32, f32)>) -> (f32, f32) {
-    let mut x = 0.0;
-    let mut y = 0.0;
-    for position in positions {
-        x += position.0;
-        y += position.1;
+/// Keys for choosing between candidate stations.
+fn candidate_key(key: Key, _modifiers: Modifiers) -> Option<Message> {
+    match key.as_ref() {
+        Key::Named(Named::ArrowUp) => Some(Message::CandidateMoved(-1)),
+        Key::Named(Named::ArrowDown) | Key::Named(Named::Tab) => Some(Message::CandidateMoved(1)),
+        Key::Named(Named::Enter) => Some(Message::CandidateConfirmed),
+        Key::Character(digit) => digit.parse::<usize>().ok()
+            .filter(|number| *number >= 1)
+            .map(|number| Message::CandidatePicked(number - 1)),
+        _ => None
+    }
+}
+
+/// Keys for moving through the suggestions while typing a guess.
+fn suggestion_key(key: Key, _modifiers: Modifiers) -> Option<Message> {
+    match key.as_ref() {
+        Key::Named(Named::ArrowUp) => Some(Message::SuggestionMoved(-1)),
+        Key::Named(Named::ArrowDown) => Some(Message::SuggestionMoved(1)),
+        Key::Named(Named::Tab) => Some(Message::SuggestionCompleted),
+        _ => None
     }
-    (x / positions.len() as f32, y / positions.len() as f32)
 }
 
 fn lerp_colour(start: &Color, end: &Color, delta: f32) -> Color {
@@ -408,8 +2115,43 @@ fn lerp_colour(start: &Color, end: &Color, delta: f32) -> Color {
     )
 }
 
+/// Draws an arrowhead just outside a marker, pointing in the given direction.
+fn draw_arrow(frame: &mut Frame, point: Point, direction: Vector, coords: &CoordinateSystem) {
+    let side = Vector::new(-direction.y, direction.x) * coords.x_dist_pixels(16.0);
+    let base = point + direction * coords.x_dist_pixels(38.0);
+    let tip = point + direction * coords.x_dist_pixels(70.0);
+
+    let arrow = Path::new(|builder| {
+        builder.move_to(tip);
+        builder.line_to(base + side);
+        builder.line_to(base - side);
+        builder.close();
+    });
+    frame.fill(&arrow, Color::WHITE);
+    frame.stroke(&arrow, Stroke::default()
+        .with_color(Color::BLACK)
+        .with_width(coords.x_dist_pixels(5.0)));
+}
+
+/// Colour for a closeness, ranging from green (close) to yellow to red (far).
+fn closeness_colour(closeness: f32) -> Color {
+    // Closeness above which we start going from yellow to green
+    const CLOSE: f32 = 5.0 / 7.0;
+
+    let red = Color::from_rgb8(255, 0, 0);
+    let yellow = Color::from_rgb8(255, 255, 0);
+    let green = Color::from_rgb8(0, 255, 0);
+
+    let closeness = closeness.clamp(0.0, 1.0);
+    if closeness >= CLOSE {
+        lerp_colour(&green, &yellow, (1.0 - closeness) / (1.0 - CLOSE))
+    } else {
+        lerp_colour(&yellow, &red, (CLOSE - closeness) / CLOSE)
+    }
+}
+
 impl Program<Message> for TubeTagApp {
-    type State = viewer::State;
+    type State = ViewerState;
 
     fn draw(
         &self,
@@ -419,50 +2161,98 @@ impl Program<Message> for TubeTagApp {
         bounds: Rectangle,
         _cursor: Cursor
     ) -> Vec<Geometry> {
-        // Clone the state and cast to PubState to extract offsets
-        let exposed_state: PubState = unsafe { std::mem::transmute(state.clone()) };
-
         self.render_cache.clear();
 
         // Rendering
         let geometry = self.render_cache.draw(renderer, bounds.size(), |frame| {
             let center = frame.center();
             let offset = Vector::new(
-                center.x - exposed_state.current_offset.x,
-                center.y - exposed_state.current_offset.y
+                center.x - state.current_offset.x,
+                center.y - state.current_offset.y
             );
 
-            let coords = CoordinateSystem::new(frame.width(), frame.height(), exposed_state.scale);
+            let coords = CoordinateSystem::new(frame.width(), frame.height(), state.scale);
 
-            let stations = if !self.viewing_map {
-                let mut guessed = vec![];
-                for station in &self.guessed_stations {
-                    guessed.push(&self.all_stations[station.clone()])
-                }
-                guessed
+            // Blind games cover the map, the viewer underneath still pans and zooms
+            if self.is_blind() {
+                frame.fill_rectangle(Point::ORIGIN, frame.size(), Color::from_rgb8(235, 235, 235));
+            }
+
+            let to_point = |offsets: &(f32, f32)| {
+                let relative_x = offsets.0 * CoordinateSystem::REL_X;
+                let relative_y = offsets.1 * CoordinateSystem::REL_Y;
+                Point::new(
+                    coords.x_dist_percent(relative_x - 0.5),
+                    coords.y_dist_percent(relative_y - 0.5)
+                ).add(offset)
+            };
+
+            // After losing, connect the closest guess to the target. While naming
+            // the station, connect the last guess to show how far off it was
+            let connected = if self.phase.is_lost() {
+                self.closest_guess()
+            } else if self.mode == GameMode::NameStation && self.phase == GamePhase::Playing {
+                self.last_guess
             } else {
-                let mut all = vec![];
-                for station in &self.all_stations {
-                    all.push(station)
-                }
-                all
+                None
             };
-            for station in stations {
+            let connected_positions = self.target_station.zip(connected).and_then(|(target_idx, connected_idx)| {
+                self.all_stations[connected_idx].map_position().zip(self.all_stations[target_idx].map_position())
+            });
+            if let Some((connected_position, target_position)) = connected_positions {
+                if !self.viewing_map {
+                    let connection = Path::line(to_point(&connected_position), to_point(&target_position));
+                    frame.stroke(&connection, Stroke::default()
+                        .with_color(Color::BLACK)
+                        .with_width(coords.x_dist_pixels(16.0)));
+                    frame.stroke(&connection, Stroke::default()
+                        .with_color(Color::WHITE)
+                        .with_width(coords.x_dist_pixels(8.0)));
+                }
+            }
+
+            // Shade the region hint, fading out towards its edge
+            if let Some(centre) = self.hint_region.filter(|_| !self.viewing_map) {
+                let centre = to_point(&centre);
+                for fraction in [1.0, 0.85, 0.7] {
+                    let region = Path::circle(centre, coords.x_dist_pixels(HINT_REGION_RADIUS * fraction));
+                    frame.fill(&region, Color::from_rgba8(0, 120, 255, 0.12));
+                }
+            }
+
+            // The target is where all the rings cross, they're measured on the map so they scale with it
+            let target_position = self.target_station.and_then(|target_idx| self.all_stations[target_idx].map_position());
+            if let (MarkerStyle::Rings, Some(target_position)) = (self.markers(), target_position) {
+                if !self.viewing_map {
+                    let target_point = to_point(&target_position);
+                    for station_idx in self.guess_log.iter() {
+                        let Some(position) = self.all_stations[station_idx].map_position() else {
+                            continue
+                        };
+                        let point = to_point(&position);
+                        let radius = point.distance(target_point);
+                        if radius == 0.0 {
+                            continue
+                        }
+                        let ring = Path::circle(point, radius);
+                        frame.stroke(&ring, Stroke::default()
+                            .with_color(Color::from_rgba8(0, 0, 0, 0.6))
+                            .with_width(coords.x_dist_pixels(6.0)));
+                    }
+                }
+            }
+
+            for station_idx in self.visible_stations() {
+                let station = &self.all_stations[station_idx];
                 for (index, offsets) in station.station_positions.iter().enumerate() {
-                    let relative_x = offsets.0 * CoordinateSystem::REL_X;
-                    let relative_y = offsets.1 * CoordinateSystem::REL_Y;
-                    let point = Point::new(
-                        coords.x_dist_percent(relative_x - 0.5),
-                        coords.y_dist_percent(relative_y - 0.5)
-                    ).add(offset);
+                    let point = to_point(offsets);
 
                     // Render station name text
-                    if index == 0 {
+                    if index == 0 && !self.is_label_hidden(station_idx) {
                         // Loop over each line in the name and render it
                         for mut name in station.get_render_lines(&point, &coords) {
                             if let Some(target_idx) = self.target_station {
-                                let target_station = &self.all_stations[target_idx];
-                                if !self.viewing_map && station.name == target_station.name {
+                                if !self.viewing_map && self.groups.same_group(station_idx, target_idx) {
                                     name.color = Color::from_rgb8(0, 255, 0);
                                     let mut shadow = name.clone();
                                     shadow.color = Color::BLACK;
@@ -481,31 +2271,48 @@ impl Program<Message> for TubeTagApp {
                         continue
                     }
 
-                    if let Some(target_idx) = self.target_station {
-                        let target_station = &self.all_stations[target_idx];
-                        let average_target_position = average_position(&target_station.station_positions);
-                        let dx = offsets.0 - average_target_position.0;
-                        let dy = offsets.1 - average_target_position.1;
-
-                        let distance = (dx * dx + dy * dy).sqrt() * CoordinateSystem::REL_Y;
-
-                        let red = Color::from_rgb8(255, 0, 0);
-                        let yellow = Color::from_rgb8(255, 255, 0);
-                        let green = Color::from_rgb8(0, 255, 0);
+                    // Draw a ring around the target once the game is lost
+                    if let Some(target_idx) = self.target_station.filter(|_| self.phase.is_lost()) {
+                        if self.groups.same_group(station_idx, target_idx) {
+                            let ring = Path::circle(point, coords.x_dist_pixels(48.0));
+                            frame.stroke(&ring, Stroke::default()
+                                .with_color(Color::from_rgb8(0, 255, 0))
+                                .with_width(coords.x_dist_pixels(10.0)));
+                        }
+                    }
 
-                        let colour = if distance > 0.7 {
-                            red
-                        } else if distance > 0.2 {
-                            lerp_colour(&yellow, &red, (distance * 2.0) - 0.4)
-                        } else {
-                            lerp_colour(&green, &yellow, distance * 5.0)
-                        };
+                    // Make the station being named easy to spot
+                    if self.is_label_hidden(station_idx) {
+                        let ring = Path::circle(point, coords.x_dist_pixels(NAMING_RING_RADIUS));
+                        frame.stroke(&ring, Stroke::default()
+                            .with_color(NAMING_RING_COLOUR)
+                            .with_width(coords.x_dist_pixels(NAMING_RING_WIDTH)));
+                    }
 
+                    // Draw a ring around markers of a repeated guess so they can be found
+                    if let Some((flash_idx, instant)) = self.flash {
+                        if self.groups.same_group(station_idx, flash_idx) && instant.elapsed() < FLASH_DURATION {
+                            let ring = Path::circle(point, coords.x_dist_pixels(48.0));
+                            frame.stroke(&ring, Stroke::default()
+                                .with_color(Color::WHITE)
+                                .with_width(coords.x_dist_pixels(10.0)));
+                        }
+                    }
 
-                        let circle = Path::circle(point, coords.x_dist_pixels(32.0));
-                        frame.fill(&circle, Color::BLACK);
-                        let circle = Path::circle(point, coords.x_dist_pixels(25.0));
-                        frame.fill(&circle, colour);
+                    // Without a target, or with rings, the marker shows the station's line instead
+                    let colour = match &self.metric {
+                        Some(metric) if self.markers() == MarkerStyle::Coloured => {
+                            closeness_colour(metric.closeness(station_idx, Some(offsets)))
+                        }
+                        _ => station.lines.first().map_or(Color::WHITE, |line_id| self.lines.get(*line_id).colour)
+                    };
+                    let circle = Path::circle(point, coords.x_dist_pixels(32.0));
+                    frame.fill(&circle, Color::BLACK);
+                    let circle = Path::circle(point, coords.x_dist_pixels(25.0));
+                    frame.fill(&circle, colour);
+
+                    if let Some(direction) = self.direction_to_target(station_idx, point, to_point) {
+                        draw_arrow(frame, point, direction, &coords);
                     }
                 }
             }
@@ -0,0 +1,277 @@
+use std::hash::Hash;
+use iced::{Element, Event, event, Length, mouse, Point, Rectangle, Size, Vector};
+use iced::advanced::{Clipboard, image, Layout, renderer, Shell, Widget};
+use iced::advanced::layout::{Limits, Node};
+use iced::advanced::renderer::Style;
+use iced::advanced::widget::Tree;
+use iced::advanced::widget::tree::{State, Tag};
+use iced::mouse::{Cursor, Interaction};
+
+const MAX_SCALE: f32 = 10.0;
+const SCALE_STEP: f32 = 0.1;
+
+/// Image viewer that can be zoomed and panned, like iced's own viewer.
+/// Its state is public, so the map markers can be drawn in the right
+/// place and the map can be moved from a widget operation.
+pub struct MapViewer<Handle> {
+    handle: Handle,
+    width: Length,
+    height: Length,
+    min_scale: f32
+}
+
+/// How far the [`MapViewer`] is zoomed in and panned across.
+#[derive(Debug, Clone, Copy)]
+pub struct ViewerState {
+    pub scale: f32,
+    pub starting_offset: Vector,
+    pub current_offset: Vector,
+    pub cursor_grabbed_at: Option<Point>
+}
+
+impl Default for ViewerState {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            starting_offset: Vector::default(),
+            current_offset: Vector::default(),
+            cursor_grabbed_at: None
+        }
+    }
+}
+
+impl ViewerState {
+    /// Moves the map so that the given offset from its centre is in the middle.
+    pub fn pan_to(&mut self, offset: Vector) {
+        self.starting_offset = offset;
+        self.current_offset = offset;
+    }
+
+    // The furthest the map can be panned in each direction
+    fn hidden_size(&self, bounds: Rectangle, image_size: Size) -> Vector {
+        Vector::new(
+            (image_size.width - bounds.width / 2.0).max(0.0).round(),
+            (image_size.height - bounds.height / 2.0).max(0.0).round()
+        )
+    }
+
+    fn offset(&self, bounds: Rectangle, image_size: Size) -> Vector {
+        let hidden = self.hidden_size(bounds, image_size);
+        Vector::new(
+            self.current_offset.x.clamp(-hidden.x, hidden.x),
+            self.current_offset.y.clamp(-hidden.y, hidden.y)
+        )
+    }
+}
+
+impl<Handle> MapViewer<Handle> {
+    pub fn new(handle: Handle) -> Self {
+        Self {
+            handle,
+            width: Length::Shrink,
+            height: Length::Shrink,
+            min_scale: 0.25
+        }
+    }
+
+    pub fn width(mut self, width: impl Into<Length>) -> Self {
+        self.width = width.into();
+        self
+    }
+
+    pub fn min_scale(mut self, min_scale: f32) -> Self {
+        self.min_scale = min_scale;
+        self
+    }
+
+    // The size of the image at the current scale, keeping its aspect ratio
+    fn image_size<Renderer>(&self, renderer: &Renderer, state: &ViewerState, bounds: Size) -> Size
+    where
+        Renderer: image::Renderer<Handle = Handle>
+    {
+        let Size { width, height } = renderer.dimensions(&self.handle);
+        let (width, height) = (width as f32, height as f32);
+        let ratio = (bounds.width / width).min(bounds.height / height);
+        if ratio < 1.0 {
+            Size::new(width * ratio * state.scale, height * ratio * state.scale)
+        } else {
+            Size::new(width * state.scale, height * state.scale)
+        }
+    }
+}
+
+impl<Message, Theme, Renderer, Handle> Widget<Message, Theme, Renderer> for MapViewer<Handle>
+where
+    Renderer: image::Renderer<Handle = Handle>,
+    Handle: Clone + Hash
+{
+    fn tag(&self) -> Tag {
+        Tag::of::<ViewerState>()
+    }
+
+    fn state(&self) -> State {
+        State::new(ViewerState::default())
+    }
+
+    fn size(&self) -> Size<Length> {
+        Size::new(self.width, self.height)
+    }
+
+    fn layout(&self, _tree: &mut Tree, renderer: &Renderer, limits: &Limits) -> Node {
+        let Size { width, height } = renderer.dimensions(&self.handle);
+        let mut size = limits.resolve(self.width, self.height, Size::new(width as f32, height as f32));
+
+        // Shrink to the image's aspect ratio unless asked to fill the space
+        let expansion = if height > width { self.width } else { self.height };
+        if let Length::Shrink | Length::Fixed(_) = expansion {
+            let aspect_ratio = width as f32 / height as f32;
+            if size.width / size.height > aspect_ratio {
+                size.width = width as f32 * size.height / height as f32;
+            } else {
+                size.height = height as f32 * size.width / width as f32;
+            }
+        }
+        Node::new(size)
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        _shell: &mut Shell<'_, Message>,
+        _viewport: &Rectangle
+    ) -> event::Status {
+        let bounds = layout.bounds();
+        let state = tree.state.downcast_mut::<ViewerState>();
+
+        match event {
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                let Some(cursor_position) = cursor.position_over(bounds) else {
+                    return event::Status::Ignored
+                };
+                let (mouse::ScrollDelta::Lines { y, .. } | mouse::ScrollDelta::Pixels { y, .. }) = delta;
+
+                let previous_scale = state.scale;
+                if y < 0.0 && previous_scale > self.min_scale || y > 0.0 && previous_scale < MAX_SCALE {
+                    state.scale = if y > 0.0 {
+                        state.scale * (1.0 + SCALE_STEP)
+                    } else {
+                        state.scale / (1.0 + SCALE_STEP)
+                    }.clamp(self.min_scale, MAX_SCALE);
+
+                    // Zoom towards the cursor
+                    let image_size = self.image_size(renderer, state, bounds.size());
+                    let factor = state.scale / previous_scale - 1.0;
+                    let adjustment = (cursor_position - bounds.center()) * factor + state.current_offset * factor;
+                    state.current_offset = Vector::new(
+                        if image_size.width > bounds.width { state.current_offset.x + adjustment.x } else { 0.0 },
+                        if image_size.height > bounds.height { state.current_offset.y + adjustment.y } else { 0.0 }
+                    );
+                }
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some(cursor_position) = cursor.position() else {
+                    return event::Status::Ignored
+                };
+                state.cursor_grabbed_at = Some(cursor_position);
+                state.starting_offset = state.current_offset;
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) if state.cursor_grabbed_at.is_some() => {
+                state.cursor_grabbed_at = None;
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                let Some(origin) = state.cursor_grabbed_at else {
+                    return event::Status::Ignored
+                };
+                let image_size = self.image_size(renderer, state, bounds.size());
+                let hidden = state.hidden_size(bounds, image_size);
+                let delta = position - origin;
+                state.current_offset = Vector::new(
+                    if bounds.width < image_size.width {
+                        (state.starting_offset.x - delta.x).clamp(-hidden.x, hidden.x)
+                    } else {
+                        0.0
+                    },
+                    if bounds.height < image_size.height {
+                        (state.starting_offset.y - delta.y).clamp(-hidden.y, hidden.y)
+                    } else {
+                        0.0
+                    }
+                );
+                event::Status::Captured
+            }
+            _ => event::Status::Ignored
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer
+    ) -> Interaction {
+        let state = tree.state.downcast_ref::<ViewerState>();
+        if state.cursor_grabbed_at.is_some() {
+            Interaction::Grabbing
+        } else if cursor.is_over(layout.bounds()) {
+            Interaction::Grab
+        } else {
+            Interaction::Idle
+        }
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Theme,
+        _style: &Style,
+        layout: Layout<'_>,
+        _cursor: Cursor,
+        _viewport: &Rectangle
+    ) {
+        let state = tree.state.downcast_ref::<ViewerState>();
+        let bounds = layout.bounds();
+        let image_size = self.image_size(renderer, state, bounds.size());
+
+        let image_top_left = Vector::new(
+            bounds.width / 2.0 - image_size.width / 2.0,
+            bounds.height / 2.0 - image_size.height / 2.0
+        );
+        let translation = image_top_left - state.offset(bounds, image_size);
+
+        renderer::Renderer::with_layer(renderer, bounds, |renderer| {
+            renderer::Renderer::with_translation(renderer, translation, |renderer| {
+                image::Renderer::draw(
+                    renderer,
+                    self.handle.clone(),
+                    image::FilterMethod::default(),
+                    Rectangle {
+                        x: bounds.x,
+                        y: bounds.y,
+                        ..Rectangle::with_size(image_size)
+                    }
+                );
+            });
+        });
+    }
+}
+
+impl<'a, Message, Theme, Renderer, Handle> From<MapViewer<Handle>> for Element<'a, Message, Theme, Renderer>
+where
+    Renderer: image::Renderer<Handle = Handle> + 'a,
+    Handle: Clone + Hash + 'a
+{
+    fn from(viewer: MapViewer<Handle>) -> Self {
+        Element::new(viewer)
+    }
+}
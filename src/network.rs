@@ -0,0 +1,279 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use crate::lines::{LineId, LineRegistry};
+use crate::stations::Station;
+
+#[derive(Debug)]
+pub enum NetworkError {
+    UnknownStation { line: String, station: String },
+    MissingLine { line: String, station: String },
+    UnusedLine { line: String, station: String },
+    Isolated { station: String }
+}
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkError::UnknownStation { line, station } => {
+                write!(f, "{} line references unknown station '{}'", line, station)
+            }
+            NetworkError::MissingLine { line, station } => {
+                write!(f, "'{}' is on the {} line but does not list it", station, line)
+            }
+            NetworkError::UnusedLine { line, station } => {
+                write!(f, "'{}' lists the {} line but is not on any of its branches", station, line)
+            }
+            NetworkError::Isolated { station } => {
+                write!(f, "'{}' is not connected to any other station", station)
+            }
+        }
+    }
+}
+
+/// A direct connection from one station to another along a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Connection {
+    pub station: usize,
+    pub line: LineId
+}
+
+/// Graph of every station (by index into the station list)
+/// and the stations it is directly connected to.
+#[derive(Debug, Default)]
+pub struct Network {
+    connections: Vec<Vec<Connection>>,
+    num_lines: usize
+}
+
+impl Network {
+    /// Builds the network from the line sequences, validating that every
+    /// station referenced exists and that the station data agrees with the lines.
+    pub fn new(stations: &[Station], lines: &LineRegistry) -> Result<Self, Vec<NetworkError>> {
+        let mut errors = vec![];
+        let mut connections: Vec<Vec<Connection>> = vec![vec![]; stations.len()];
+
+        let indices: HashMap<&str, usize> = stations.iter()
+            .enumerate()
+            .map(|(idx, station)| (station.name.as_str(), idx))
+            .collect();
+
+        for (line_id, line) in lines.iter() {
+            let mut on_line = vec![false; stations.len()];
+
+            for branch in &line.branches {
+                let mut previous: Option<usize> = None;
+                for name in branch {
+                    let Some(&current) = indices.get(name.as_str()) else {
+                        errors.push(NetworkError::UnknownStation {
+                            line: line.name.clone(),
+                            station: name.clone()
+                        });
+                        // We can't link across an unknown station
+                        previous = None;
+                        continue
+                    };
+                    on_line[current] = true;
+
+                    if let Some(previous) = previous {
+                        Self::connect(&mut connections, previous, current, line_id);
+                    }
+                    previous = Some(current);
+                }
+            }
+
+            // Check the stations agree with the line
+            for (station_idx, station) in stations.iter().enumerate() {
+                let lists_line = station.lines.contains(&line_id);
+                if on_line[station_idx] && !lists_line {
+                    errors.push(NetworkError::MissingLine {
+                        line: line.name.clone(),
+                        station: station.name.clone()
+                    });
+                } else if !on_line[station_idx] && lists_line {
+                    errors.push(NetworkError::UnusedLine {
+                        line: line.name.clone(),
+                        station: station.name.clone()
+                    });
+                }
+            }
+        }
+
+        for (station_idx, station) in stations.iter().enumerate() {
+            if connections[station_idx].is_empty() {
+                errors.push(NetworkError::Isolated { station: station.name.clone() });
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors)
+        }
+        Ok(Self {
+            connections,
+            num_lines: lines.iter().count()
+        })
+    }
+
+    fn connect(connections: &mut [Vec<Connection>], first: usize, second: usize, line: LineId) {
+        let forward = Connection { station: second, line };
+        if !connections[first].contains(&forward) {
+            connections[first].push(forward);
+        }
+        let backward = Connection { station: first, line };
+        if !connections[second].contains(&backward) {
+            connections[second].push(backward);
+        }
+    }
+
+    /// The stations directly connected to the given station, on any line.
+    pub fn neighbours(&self, station: usize) -> Vec<usize> {
+        let mut neighbours: Vec<usize> = self.connections[station].iter()
+            .map(|connection| connection.station)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours
+    }
+
+    /// The shortest sequence of stations from `from` to `to`, including both ends.
+    /// Returns `None` if there is no route between them.
+    pub fn shortest_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let mut previous: Vec<Option<usize>> = vec![None; self.connections.len()];
+        let mut visited = vec![false; self.connections.len()];
+        let mut queue = VecDeque::from([from]);
+        visited[from] = true;
+
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut path = vec![to];
+                let mut step = to;
+                while let Some(prev) = previous[step] {
+                    path.push(prev);
+                    step = prev;
+                }
+                path.reverse();
+                return Some(path)
+            }
+
+            for neighbour in self.neighbours(current) {
+                if !visited[neighbour] {
+                    visited[neighbour] = true;
+                    previous[neighbour] = Some(current);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        None
+    }
+
+    /// The number of stops between two stations.
+    pub fn hops(&self, from: usize, to: usize) -> Option<usize> {
+        self.shortest_path(from, to).map(|path| path.len() - 1)
+    }
+
+    /// The cost of reaching every station from `source`, where each stop costs 1
+    /// and each change of line along the way costs an extra `change_cost`.
+    /// Stations that cannot be reached have no cost.
+    pub fn costs_from(&self, source: usize, change_cost: usize) -> Vec<Option<usize>> {
+        // We search over (station, line) pairs so we know when we change lines
+        let mut best: Vec<Vec<Option<usize>>> = vec![vec![None; self.num_lines]; self.connections.len()];
+        let mut queue = BinaryHeap::new();
+        for connection in &self.connections[source] {
+            queue.push(Reverse((1, connection.station, connection.line)));
+        }
+
+        while let Some(Reverse((cost, station, line))) = queue.pop() {
+            if best[station][line.0].is_some() {
+                continue
+            }
+            best[station][line.0] = Some(cost);
+
+            for connection in &self.connections[station] {
+                if best[connection.station][connection.line.0].is_some() {
+                    continue
+                }
+                let change = if connection.line == line { 0 } else { change_cost };
+                queue.push(Reverse((cost + 1 + change, connection.station, connection.line)));
+            }
+        }
+
+        let mut costs: Vec<Option<usize>> = best.iter()
+            .map(|lines| lines.iter().flatten().min().copied())
+            .collect();
+        costs[source] = Some(0);
+        costs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::Color;
+    use super::*;
+    use crate::lines::{Line, RailNetwork};
+
+    // a - b - c - d on the red line, b - e - d on the blue line and f - g on their own
+    fn network() -> Network {
+        let line = |id: &str, branch: &[&str]| Line {
+            id: id.to_string(),
+            name: id.to_string(),
+            colour: Color::BLACK,
+            network: RailNetwork::Underground,
+            branches: vec![branch.iter().map(|name| name.to_string()).collect()]
+        };
+        let lines = LineRegistry::new(vec![
+            line("red", &["a", "b", "c", "d"]),
+            line("blue", &["b", "e", "d"]),
+            line("green", &["f", "g"])
+        ]).unwrap();
+
+        let station = |name: &str, line_ids: &[&str]| Station {
+            name: name.to_string(),
+            line_ids: line_ids.iter().map(|id| id.to_string()).collect(),
+            ..Default::default()
+        };
+        let mut stations = vec![
+            station("a", &["red"]),
+            station("b", &["red", "blue"]),
+            station("c", &["red"]),
+            station("d", &["red", "blue"]),
+            station("e", &["blue"]),
+            station("f", &["green"]),
+            station("g", &["green"])
+        ];
+        lines.resolve(&mut stations).unwrap();
+        Network::new(&stations, &lines).unwrap()
+    }
+
+    #[test]
+    fn neighbours_are_shared_across_lines() {
+        let network = network();
+        assert_eq!(network.neighbours(1), vec![0, 2, 4]);
+        assert_eq!(network.neighbours(3), vec![2, 4]);
+        assert_eq!(network.neighbours(5), vec![6]);
+    }
+
+    #[test]
+    fn shortest_path_takes_the_fewest_stops() {
+        let network = network();
+        assert_eq!(network.shortest_path(0, 0), Some(vec![0]));
+        assert_eq!(network.shortest_path(0, 4), Some(vec![0, 1, 4]));
+        assert_eq!(network.shortest_path(0, 5), None);
+        assert_eq!(network.hops(0, 3), Some(3));
+        assert_eq!(network.hops(5, 6), Some(1));
+        assert_eq!(network.hops(6, 0), None);
+    }
+
+    #[test]
+    fn costs_from_counts_stops_and_changes() {
+        let network = network();
+        assert_eq!(
+            network.costs_from(0, 0),
+            vec![Some(0), Some(1), Some(2), Some(3), Some(2), None, None]
+        );
+        // Changing at b to reach e now costs more than staying on the red line
+        assert_eq!(
+            network.costs_from(0, 5),
+            vec![Some(0), Some(1), Some(2), Some(3), Some(7), None, None]
+        );
+    }
+}
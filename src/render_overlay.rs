@@ -2,7 +2,7 @@ use iced::{Element, Event, event, Length, mouse, Point, Rectangle, Size, Vector}
 use iced::advanced::{Clipboard, Layout, Overlay, renderer, Shell, Widget};
 use iced::advanced::layout::{Limits, Node};
 use iced::advanced::renderer::Style;
-use iced::advanced::widget::Tree;
+use iced::advanced::widget::{Operation, Tree};
 use iced::advanced::widget::tree::{State, Tag};
 use iced::event::Status;
 use iced::mouse::{Cursor, Interaction};
@@ -18,12 +18,19 @@ pub struct RenderOverlay<'a, Message, Theme, Renderer> {
     overlay: Element<'a, Message, Theme, Renderer>
 }
 
+/// The underlying element's state along with its bounds,
+/// this is what custom operations on a [`RenderOverlay`] are given.
+pub struct OverlaidState {
+    pub tree: Tree,
+    pub bounds: Rectangle
+}
+
 impl<'a, Message, Theme, Renderer> RenderOverlay<'a, Message, Theme, Renderer> {
     pub fn new(
         underlay: impl Into<Element<'a, Message, Theme, Renderer>>,
         overlay: impl Into<Element<'a, Message, Theme, Renderer>>
     ) -> RenderOverlay<'a, Message, Theme, Renderer> {
-        return Self {
+        Self {
             underlay: underlay.into(),
             overlay: overlay.into()
         }
@@ -56,7 +63,7 @@ where
         // Rendering the overlay here does not guarantee that it will
         // actually be on top despite calling the draw method after
         self.underlay.as_widget().draw(
-            &tree,
+            tree,
             renderer,
             theme,
             style,
@@ -74,6 +81,22 @@ where
         self.underlay.as_widget().state()
     }
 
+    fn operate(
+        &self,
+        state: &mut Tree,
+        layout: Layout<'_>,
+        _renderer: &Renderer,
+        operation: &mut dyn Operation<Message>
+    ) {
+        // Most states only make sense alongside the bounds, so we pass them in too
+        let mut overlaid = OverlaidState {
+            tree: std::mem::replace(state, Tree::empty()),
+            bounds: layout.bounds()
+        };
+        operation.custom(&mut overlaid, None);
+        *state = overlaid.tree;
+    }
+
     fn on_event(
         &mut self,
         state: &mut Tree,
@@ -207,7 +230,7 @@ where
         renderer: &Renderer
     ) -> Interaction {
         // We have to do this check otherwise we will consume events for other widgets
-        if cursor.is_over(viewport.clone()) {
+        if cursor.is_over(*viewport) {
             return self.underlay.as_widget().mouse_interaction(self.state, layout, cursor, viewport, renderer)
         }
         Interaction::Idle
@@ -1,4 +1,42 @@
+use std::fs;
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
 pub fn convert_relative_path(path: &str) -> String
 {
-    return format!("{}/{}", env!("CARGO_MANIFEST_DIR"), path);
-}
\ No newline at end of file
+    format!("{}/{}", env!("CARGO_MANIFEST_DIR"), path)
+}
+
+/// Where a save file is kept, saves go in the player's data directory
+/// rather than next to the assets so that they survive reinstalling.
+fn save_path(name: &str) -> std::io::Result<PathBuf> {
+    dirs::data_dir()
+        .map(|dir| dir.join("tube_tag").join(name))
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "no data directory to save to"))
+}
+
+/// Reads a save file, returns `None` if it doesn't exist or can't be read.
+pub fn load_save<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let file = File::open(save_path(name).ok()?).ok()?;
+    serde_json::from_reader(file).ok()
+}
+
+pub fn write_save<T: Serialize>(name: &str, value: &T) -> std::io::Result<()> {
+    let path = save_path(name)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    serde_json::to_writer_pretty(File::create(path)?, value)?;
+    Ok(())
+}
+
+/// Removes a save file, there being nothing to remove isn't an error.
+pub fn delete_save(name: &str) -> std::io::Result<()> {
+    match fs::remove_file(save_path(name)?) {
+        Err(error) if error.kind() != ErrorKind::NotFound => Err(error),
+        _ => Ok(())
+    }
+}
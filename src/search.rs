@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use regex::Regex;
+use simsearch::{SearchOptions, SimSearch};
+use crate::groups::StationGroups;
+use crate::stations::Station;
+
+/// Fuzzy search over every station's name and aliases.
+#[derive(Default)]
+pub struct StationSearch {
+    search_engine: SimSearch<usize>,
+    // Exact matches always come first
+    exact: HashMap<String, Vec<usize>>
+}
+
+impl StationSearch {
+    pub fn new(stations: &[Station], groups: &StationGroups) -> Self {
+        let mut search_engine = SimSearch::new_with(
+            SearchOptions::new().threshold(0.75).stop_whitespace(false).levenshtein(true)
+        );
+        let mut exact: HashMap<String, Vec<usize>> = HashMap::new();
+        for (station_idx, tokens) in Self::station_tokens(stations, groups).into_iter().enumerate() {
+            for token in &tokens {
+                let matches = exact.entry(token.clone()).or_default();
+                if !matches.contains(&station_idx) {
+                    matches.push(station_idx);
+                }
+            }
+            let tokens: Vec<&str> = tokens.iter().map(String::as_str).collect();
+            search_engine.insert_tokens(station_idx, &tokens);
+        }
+        Self { search_engine, exact }
+    }
+
+    /// Stations matching the query, best match first.
+    pub fn search(&self, query: &str) -> Vec<usize> {
+        let query = normalise(query);
+        let mut results = self.exact.get(&query).cloned().unwrap_or_default();
+        for station_idx in self.search_engine.search(&query) {
+            if !results.contains(&station_idx) {
+                results.push(station_idx);
+            }
+        }
+        results
+    }
+
+    /// Stations whose name or an alias is exactly the query.
+    pub fn search_exact(&self, query: &str) -> Vec<usize> {
+        self.exact.get(&normalise(query)).cloned().unwrap_or_default()
+    }
+
+    /// Stations to suggest while the query is still being typed,
+    /// names starting with the query come before fuzzy matches.
+    pub fn suggest(&self, query: &str) -> Vec<usize> {
+        let normalised = normalise(query);
+        if normalised.is_empty() {
+            return vec![]
+        }
+
+        // Shortest names first, they are the closest to being complete
+        let mut prefixed: Vec<(&String, &Vec<usize>)> = self.exact.iter()
+            .filter(|(token, _)| token.starts_with(&normalised))
+            .collect();
+        prefixed.sort_by_key(|(token, _)| (token.len(), *token));
+
+        let mut results = vec![];
+        for (_, station_indices) in prefixed {
+            for station_idx in station_indices {
+                if !results.contains(station_idx) {
+                    results.push(*station_idx);
+                }
+            }
+        }
+        for station_idx in self.search(query) {
+            if !results.contains(&station_idx) {
+                results.push(station_idx);
+            }
+        }
+        results
+    }
+
+    fn station_tokens(stations: &[Station], groups: &StationGroups) -> Vec<Vec<String>> {
+        let regex = Regex::new(r"\((.*?)\)").unwrap();
+        stations.iter().enumerate().map(|(station_idx, station)| {
+            let name = &station.name;
+            let mut tokens = vec![normalise(name)];
+            // Every station in a group can be found by the group's name
+            let group_name = normalise(&groups.group(station_idx).name);
+            if !tokens.contains(&group_name) {
+                tokens.push(group_name);
+            }
+            if regex.is_match(name) {
+                // We insert the name without brackets too
+                tokens.push(normalise(&regex.replace_all(name, "")));
+            }
+            for alias in &station.aliases {
+                tokens.push(normalise(alias));
+            }
+            tokens
+        }).collect()
+    }
+}
+
+/// Normalises a station name or query so that common ways
+/// of writing the same name are equal, for example:
+/// "Shepherd's Bush" and "Shepherds Bush",
+/// "Elephant & Castle" and "Elephant and Castle",
+/// "St. Paul's" and "Saint Pauls",
+/// "Liverpool St" and "Liverpool Street",
+/// "Goldhawk Rd" and "Goldhawk Road",
+/// "Kensington (Olympia)" and "Kensington Olympia".
+pub fn normalise(query: &str) -> String {
+    let cleaned: String = query.to_lowercase()
+        .replace('&', " and ")
+        .chars()
+        .filter(|c| !matches!(c, '\'' | '\u{2019}' | '.' | ',' | '(' | ')'))
+        .map(|c| if c == '-' { ' ' } else { c })
+        .collect();
+
+    let words: Vec<&str> = cleaned.split_whitespace().collect();
+    let last = words.len().saturating_sub(1);
+    words.iter()
+        .enumerate()
+        .map(|(idx, word)| match *word {
+            // "St" at the end of a name is a street, otherwise it's a saint
+            "st" if idx == last => "street",
+            "st" => "saint",
+            "rd" => "road",
+            word => word
+        })
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalise_ignores_case_and_punctuation() {
+        assert_eq!(normalise("King's Cross St. Pancras"), "kings cross saint pancras");
+        assert_eq!(normalise("  Elephant & Castle "), "elephant and castle");
+        assert_eq!(normalise("Harrow-on-the-Hill"), "harrow on the hill");
+        assert_eq!(normalise("Edgware Road (Bakerloo)"), "edgware road bakerloo");
+    }
+
+    #[test]
+    fn normalise_expands_abbreviations() {
+        assert_eq!(normalise("Baker St"), "baker street");
+        assert_eq!(normalise("St John's Wood"), "saint johns wood");
+        assert_eq!(normalise("Edgware Rd"), "edgware road");
+    }
+}
@@ -1,4 +1,5 @@
 use std::f32::consts::SQRT_2;
+use std::fmt::{Display, Formatter};
 use std::ops::{Add, Mul};
 use iced::{Color, Pixels, Point, Vector};
 use iced::advanced::text::{LineHeight, Shaping};
@@ -6,18 +7,90 @@ use iced::alignment::{Horizontal, Vertical};
 use iced::widget::canvas::Text;
 use serde::{Deserialize};
 use crate::{CoordinateSystem, UNDERGROUND_FONT};
+use crate::lines::LineId;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Station {
     pub name: String,
+    // Line ids as written in the data, these are resolved into `lines` on load
+    #[serde(rename = "lines", default)]
+    pub line_ids: Vec<String>,
+    #[serde(skip)]
+    pub lines: Vec<LineId>,
+    // Stations the map doesn't draw have no positions
     #[serde(default)]
-    pub lines: Vec<String>,
     pub station_positions: Vec<(f32, f32)>,
+    // Real-world (latitude, longitude) for each entrance
+    #[serde(default)]
+    pub geo: Vec<(f64, f64)>,
+    // Boundary stations are in two zones
+    #[serde(default)]
+    pub zones: Vec<u8>,
+    // Other names players might use, these are only used for searching
+    #[serde(default)]
+    pub aliases: Vec<String>,
     #[serde(default)]
     pub name_data: NameData
 }
 
+/// Where the target's fare zones are relative to a guess.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoneComparison {
+    Lower,
+    Same,
+    Higher
+}
+
+impl Display for ZoneComparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ZoneComparison::Lower => write!(f, "the target is in a lower zone"),
+            ZoneComparison::Same => write!(f, "the target is in the same zone"),
+            ZoneComparison::Higher => write!(f, "the target is in a higher zone")
+        }
+    }
+}
+
 impl Station {
+    /// Where the station is on the map, if it is drawn there.
+    pub fn map_position(&self) -> Option<(f32, f32)> {
+        self.station_positions.first().copied()
+    }
+
+    /// The average real-world position of the station, if we know it.
+    pub fn geo_position(&self) -> Option<(f64, f64)> {
+        if self.geo.is_empty() {
+            return None
+        }
+        let count = self.geo.len() as f64;
+        let latitude = self.geo.iter().map(|position| position.0).sum::<f64>() / count;
+        let longitude = self.geo.iter().map(|position| position.1).sum::<f64>() / count;
+        Some((latitude, longitude))
+    }
+
+    /// Compares the target's zones to this station's zones,
+    /// sharing any zone (for boundary stations) counts as the same.
+    pub fn compare_zones(&self, target: &Station) -> Option<ZoneComparison> {
+        let lowest = *self.zones.iter().min()?;
+        let highest = *self.zones.iter().max()?;
+        let target_lowest = *target.zones.iter().min()?;
+        let target_highest = *target.zones.iter().max()?;
+
+        if target_highest < lowest {
+            Some(ZoneComparison::Lower)
+        } else if target_lowest > highest {
+            Some(ZoneComparison::Higher)
+        } else {
+            Some(ZoneComparison::Same)
+        }
+    }
+
+    /// The zones as they appear on a map, e.g. "Zone 2/3".
+    pub fn zones_name(&self) -> String {
+        let zones: Vec<String> = self.zones.iter().map(|zone| zone.to_string()).collect();
+        format!("Zone {}", zones.join("/"))
+    }
+
     pub fn get_render_lines(&self, point: &Point, context: &CoordinateSystem) -> Vec<Text> {
         let size = context.y_dist_pixels(36.0);
         let unit_offset = self.name_data.offset;
@@ -47,7 +120,7 @@ impl Station {
     }
 
     fn name_to_text(
-        name: &String,
+        name: &str,
         point: &Point,
         offset: Vector,
         size: f32,
@@ -55,7 +128,7 @@ impl Station {
         vertical: Vertical
     ) -> Text {
         Text {
-            content: name.clone(),
+            content: name.to_string(),
             position: point.add(offset),
             color: Color::from_rgb8(0x1B, 0x40, 0x94),
             size: Pixels(size),
@@ -119,4 +192,28 @@ impl Anchor {
             Anchor::NorthWest => (Horizontal::Right, Vertical::Bottom)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn station(zones: &[u8]) -> Station {
+        Station { zones: zones.to_vec(), ..Default::default() }
+    }
+
+    #[test]
+    fn compare_zones_finds_the_targets_direction() {
+        assert_eq!(station(&[2]).compare_zones(&station(&[1])), Some(ZoneComparison::Lower));
+        assert_eq!(station(&[2]).compare_zones(&station(&[2])), Some(ZoneComparison::Same));
+        assert_eq!(station(&[2]).compare_zones(&station(&[4])), Some(ZoneComparison::Higher));
+    }
+
+    #[test]
+    fn compare_zones_treats_shared_zones_as_the_same() {
+        assert_eq!(station(&[2, 3]).compare_zones(&station(&[3])), Some(ZoneComparison::Same));
+        assert_eq!(station(&[1]).compare_zones(&station(&[1, 2])), Some(ZoneComparison::Same));
+        assert_eq!(station(&[2, 3]).compare_zones(&station(&[4, 5])), Some(ZoneComparison::Higher));
+        assert_eq!(station(&[]).compare_zones(&station(&[1])), None);
+    }
+}
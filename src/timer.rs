@@ -0,0 +1,56 @@
+use std::time::{Duration, Instant};
+
+/// Measures how long a game has been played for, it can be
+/// stopped and started again without losing the time so far.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stopwatch {
+    banked: Duration,
+    running_since: Option<Instant>
+}
+
+impl Stopwatch {
+    /// A stopped stopwatch that has already counted the given time.
+    pub fn from_elapsed(elapsed: Duration) -> Self {
+        Self { banked: elapsed, running_since: None }
+    }
+
+    pub fn start(&mut self) {
+        if self.running_since.is_none() {
+            self.running_since = Some(Instant::now());
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(since) = self.running_since.take() {
+            self.banked += since.elapsed();
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.banked + self.running_since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+}
+
+/// Formats a duration as minutes and seconds, or hours if needed, e.g. "4:05" or "1:02:03".
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_duration_pads_minutes_and_seconds() {
+        assert_eq!(format_duration(Duration::ZERO), "0:00");
+        assert_eq!(format_duration(Duration::from_millis(245_900)), "4:05");
+        assert_eq!(format_duration(Duration::from_secs(3599)), "59:59");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+    }
+}